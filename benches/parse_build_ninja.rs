@@ -0,0 +1,102 @@
+// Copyright 2026 ninja-to-soong authors
+// SPDX-License-Identifier: Apache-2.0
+
+// Measure the time needed to parse a Ninja build directory:
+//   cargo bench --bench parse_build_ninja [-- <build_path>]
+// By default, the llvm-project build directory generated by `ninja-to-soong llvm-project` is used.
+// Without it, a CMake build directory is generated.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use ninja_to_soong::context::*;
+use ninja_to_soong::ninja_parser::*;
+
+const ITERATIONS: u32 = 5;
+// Size of the generated build directory.
+const GENERATED_LIBRARIES: usize = 400;
+const GENERATED_SOURCES_PER_LIBRARY: usize = 25;
+
+fn generate_build_path(build_path: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(build_path)?;
+    let mut rules = String::new();
+    let mut build = String::from("include rules.ninja\n\n");
+    for lib in 0..GENERATED_LIBRARIES {
+        rules += &format!(
+            "rule CXX_COMPILER__lib{lib}_Release\n  \
+             depfile = $DEP_FILE\n  deps = gcc\n  \
+             command = clang++ $DEFINES $INCLUDES $FLAGS -MD -MT $out -MF $DEP_FILE -o $out -c $in\n\n\
+             rule CXX_STATIC_LIBRARY_LINKER__lib{lib}_Release\n  \
+             command = rm -f $TARGET_FILE && llvm-ar qc $TARGET_FILE $LINK_FLAGS $in\n\n"
+        );
+        let mut objects = Vec::new();
+        for src in 0..GENERATED_SOURCES_PER_LIBRARY {
+            let object = format!("lib/lib{lib}/CMakeFiles/lib{lib}.dir/src{src}.cpp.o");
+            build += &format!(
+                "build {object}: CXX_COMPILER__lib{lib}_Release /src/lib/lib{lib}/src{src}.cpp\n  \
+                 DEFINES = -D_GNU_SOURCE -D__STDC_CONSTANT_MACROS -D__STDC_LIMIT_MACROS\n  \
+                 DEP_FILE = {object}.d\n  \
+                 FLAGS = -O3 -DNDEBUG -std=c++17 -fno-exceptions -fno-rtti\n  \
+                 INCLUDES = -I/src/lib/lib{lib} -I/src/include -Iinclude\n  \
+                 OBJECT_DIR = lib/lib{lib}/CMakeFiles/lib{lib}.dir\n\n"
+            );
+            objects.push(object);
+        }
+        build += &format!(
+            "build lib/liblib{lib}.a: CXX_STATIC_LIBRARY_LINKER__lib{lib}_Release {0}\n  \
+             TARGET_FILE = lib/liblib{lib}.a\n\n\
+             build lib{lib}: phony lib/liblib{lib}.a\n\n",
+            objects.join(" ")
+        );
+    }
+    fs::write(build_path.join("rules.ninja"), rules)?;
+    fs::write(build_path.join("build.ninja"), build)
+}
+
+fn get_build_path() -> PathBuf {
+    // Cargo passes '--bench' to benchmarks without harness.
+    if let Some(path) = env::args().skip(1).find(|arg| !arg.starts_with("--")) {
+        let build_path = PathBuf::from(path);
+        if !build_path.join("build.ninja").exists() {
+            panic!("{build_path:#?} has no build.ninja");
+        }
+        return build_path;
+    }
+    let temp_path = match env::var("N2S_TMP_PATH") {
+        Ok(dir) => PathBuf::from(dir),
+        Err(_) => env::temp_dir(),
+    }
+    .join("ninja-to-soong");
+    let build_path = temp_path.join("llvm-project");
+    if build_path.join("build.ninja").exists() {
+        return build_path;
+    }
+    let build_path = temp_path.join("parse-build-ninja-bench");
+    if let Err(err) = generate_build_path(&build_path) {
+        panic!("Could not generate {build_path:#?}: {err}");
+    }
+    build_path
+}
+
+fn main() {
+    let build_path = get_build_path();
+    // A default context has no Ninja cache, every iteration parses the build directory.
+    let ctx = Context::default();
+    let mut durations = Vec::new();
+    let mut targets = 0;
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        match parse_build_ninja("cmake", &build_path, &ctx) {
+            Ok(parsed_targets) => targets = parsed_targets.len(),
+            Err(err) => panic!("Could not parse {build_path:#?}: {err}"),
+        }
+        durations.push(start.elapsed());
+    }
+    let min = durations.iter().min().unwrap();
+    let mean = durations.iter().sum::<Duration>() / ITERATIONS;
+    println!(
+        "{build_path:#?}: {targets} targets, min {min:?}, mean {mean:?} ({ITERATIONS} iterations)"
+    );
+}
@@ -0,0 +1,470 @@
+// Copyright 2026 ninja-to-soong authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::utils::*;
+
+const COMPILE_COMMANDS_FILE: &str = "compile_commands.json";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompileCommandsMode {
+    // Fail when the flags parsed from the Ninja files differ from the compilation database.
+    Check,
+    // Use the flags of the compilation database instead of the ones parsed from the Ninja files.
+    Override,
+}
+
+impl CompileCommandsMode {
+    pub fn new(mode: &str) -> Result<Self, String> {
+        match mode {
+            "check" => Ok(Self::Check),
+            "override" => Ok(Self::Override),
+            _ => error!(
+                "Unknown compile_commands.json mode '{mode}', expected 'check' or 'override'"
+            ),
+        }
+    }
+}
+
+// Flags taking the next argument of the command as their value.
+pub const FLAGS_WITH_ARGUMENT: &[&str] = &[
+    "-MF",
+    "-MT",
+    "-MQ",
+    "-include",
+    "-imacros",
+    "-iquote",
+    "-idirafter",
+    "-Xclang",
+    "-Xassembler",
+    "-Xpreprocessor",
+    "-mllvm",
+    "-target",
+    "--sysroot",
+    "-isysroot",
+    "-arch",
+    "-x",
+];
+// Dependency files are generated by Soong itself.
+const DEPENDENCY_FLAGS: &[&str] = &["-MF", "-MT", "-MQ"];
+
+#[derive(Debug, Default, Clone)]
+pub struct CompileCommandFlags {
+    pub defines: Vec<String>,
+    pub includes: Vec<PathBuf>,
+    pub cflags: Vec<String>,
+}
+
+fn diff_flags<V>(name: &str, parsed: &[V], expected: &[V]) -> Option<String>
+where
+    V: Ord + Clone + std::fmt::Debug,
+{
+    let sorted = |values: &[V]| {
+        let mut values = values.to_vec();
+        values.sort_unstable();
+        values.dedup();
+        values
+    };
+    let (parsed, expected) = (sorted(parsed), sorted(expected));
+    if parsed == expected {
+        return None;
+    }
+    let missing = expected
+        .iter()
+        .filter(|value| !parsed.contains(value))
+        .collect::<Vec<_>>();
+    let unexpected = parsed
+        .iter()
+        .filter(|value| !expected.contains(value))
+        .collect::<Vec<_>>();
+    Some(format!(
+        "{name}: missing {missing:?}, unexpected {unexpected:?}"
+    ))
+}
+
+impl CompileCommandFlags {
+    pub fn check(&self, parsed: &CompileCommandFlags) -> Result<(), String> {
+        let diffs = [
+            diff_flags("defines", &parsed.defines, &self.defines),
+            diff_flags("includes", &parsed.includes, &self.includes),
+            diff_flags("cflags", &parsed.cflags, &self.cflags),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+        if !diffs.is_empty() {
+            return Err(diffs.join("\n"));
+        }
+        Ok(())
+    }
+}
+
+pub enum JsonValue {
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+    // Numbers, booleans & null are not used by compilation databases.
+    Literal,
+}
+
+// Minimal JSON parser, only what is needed to read compilation databases.
+struct JsonParser<'a>(Peekable<Chars<'a>>);
+
+impl JsonParser<'_> {
+    fn skip_whitespaces(&mut self) {
+        while self.0.next_if(|c| c.is_ascii_whitespace()).is_some() {}
+    }
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_whitespaces();
+        match self.0.next() {
+            Some(c) if c == expected => Ok(()),
+            c => error!("expected '{expected}', found {c:?}"),
+        }
+    }
+    fn parse_hex(&mut self) -> Result<u32, String> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let Some(digit) = self.0.next().and_then(|c| c.to_digit(16)) else {
+                return error!("invalid unicode escape");
+            };
+            code = code * 16 + digit;
+        }
+        Ok(code)
+    }
+    fn parse_unicode(&mut self) -> Result<char, String> {
+        let mut code = self.parse_hex()?;
+        if (0xD800..0xDC00).contains(&code) {
+            if self.0.next() != Some('\\') || self.0.next() != Some('u') {
+                return error!("invalid unicode surrogate pair");
+            }
+            let low = self.parse_hex()?;
+            if !(0xDC00..=0xDFFF).contains(&low) {
+                return error!("invalid unicode surrogate pair");
+            }
+            code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+        }
+        match char::from_u32(code) {
+            Some(c) => Ok(c),
+            None => error!("invalid unicode code point {code:#x}"),
+        }
+    }
+    fn parse_string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut string = String::new();
+        loop {
+            string.push(match self.0.next() {
+                Some('"') => return Ok(string),
+                Some('\\') => match self.0.next() {
+                    Some('b') => '\u{8}',
+                    Some('f') => '\u{c}',
+                    Some('n') => '\n',
+                    Some('r') => '\r',
+                    Some('t') => '\t',
+                    Some('u') => self.parse_unicode()?,
+                    Some(c @ ('"' | '\\' | '/')) => c,
+                    c => return error!("invalid escape {c:?}"),
+                },
+                Some(c) => c,
+                None => return error!("unterminated string"),
+            });
+        }
+    }
+    fn parse_sequence<F>(&mut self, end: char, mut parse_item: F) -> Result<(), String>
+    where
+        F: FnMut(&mut Self) -> Result<(), String>,
+    {
+        self.0.next();
+        self.skip_whitespaces();
+        if self.0.next_if_eq(&end).is_some() {
+            return Ok(());
+        }
+        loop {
+            parse_item(self)?;
+            self.skip_whitespaces();
+            match self.0.next() {
+                Some(',') => (),
+                Some(c) if c == end => return Ok(()),
+                c => return error!("expected ',' or '{end}', found {c:?}"),
+            }
+        }
+    }
+    fn parse_value(&mut self) -> Result<JsonValue, String> {
+        self.skip_whitespaces();
+        match self.0.peek() {
+            Some('"') => Ok(JsonValue::String(self.parse_string()?)),
+            Some('[') => {
+                let mut values = Vec::new();
+                self.parse_sequence(']', |parser| {
+                    values.push(parser.parse_value()?);
+                    Ok(())
+                })?;
+                Ok(JsonValue::Array(values))
+            }
+            Some('{') => {
+                let mut members = Vec::new();
+                self.parse_sequence('}', |parser| {
+                    let key = parser.parse_string()?;
+                    parser.expect(':')?;
+                    members.push((key, parser.parse_value()?));
+                    Ok(())
+                })?;
+                Ok(JsonValue::Object(members))
+            }
+            Some(c) if c.is_ascii_alphanumeric() || *c == '-' => {
+                while self
+                    .0
+                    .next_if(|c| c.is_ascii_alphanumeric() || ['-', '+', '.'].contains(c))
+                    .is_some()
+                {}
+                Ok(JsonValue::Literal)
+            }
+            c => error!("unexpected {c:?}"),
+        }
+    }
+}
+
+// Parse the content of a JSON file, compilation database or build system introspection data.
+pub fn parse_json(content: &str) -> Result<JsonValue, String> {
+    let mut parser = JsonParser(content.chars().peekable());
+    let value = parser.parse_value()?;
+    parser.skip_whitespaces();
+    match parser.0.next() {
+        Some(c) => error!("unexpected {c:?} after the JSON value"),
+        None => Ok(value),
+    }
+}
+
+// Split a shell command without unquoting its arguments, to compare them with the flags found in the
+// Ninja files.
+pub fn split_command(command: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut arg = String::new();
+    let mut quote = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                arg.push(c);
+                arg.extend(chars.next());
+            }
+            '"' | '\'' if quote.is_none() => {
+                quote = Some(c);
+                arg.push(c);
+            }
+            c if Some(c) == quote => {
+                quote = None;
+                arg.push(c);
+            }
+            c if c.is_whitespace() && quote.is_none() => {
+                if !arg.is_empty() {
+                    args.push(std::mem::take(&mut arg));
+                }
+            }
+            c => arg.push(c),
+        }
+    }
+    if !arg.is_empty() {
+        args.push(arg);
+    }
+    args
+}
+
+// Return the flags of a command along with its output.
+fn get_flags(
+    args: Vec<String>,
+    file: &Path,
+    directory: &Path,
+) -> (CompileCommandFlags, Option<PathBuf>) {
+    let mut flags = CompileCommandFlags::default();
+    let mut output = None;
+    // Skip the compiler and its potential launchers.
+    let mut args = args.into_iter().skip_while(|arg| !arg.starts_with("-"));
+    while let Some(arg) = args.next() {
+        if let Some(define) = arg.strip_prefix("-D") {
+            flags.defines.extend(if define.is_empty() {
+                args.next()
+            } else {
+                Some(String::from(define))
+            });
+        } else if let Some(include) = arg
+            .strip_prefix("-isystem")
+            .or_else(|| arg.strip_prefix("-I"))
+        {
+            let include = if include.is_empty() {
+                args.next()
+            } else {
+                Some(String::from(include))
+            };
+            flags
+                .includes
+                .extend(include.map(|include| canonicalize_path(include, directory)));
+        } else if arg == "-o" {
+            output = args
+                .next()
+                .map(|output| canonicalize_path(output, directory));
+        } else if FLAGS_WITH_ARGUMENT.contains(&arg.as_str()) {
+            // Flags are kept with their value, to compare them as a whole.
+            let Some(value) = args.next() else {
+                flags.cflags.push(arg);
+                continue;
+            };
+            if !DEPENDENCY_FLAGS.contains(&arg.as_str()) {
+                flags.cflags.push(format!("{arg} {value}"));
+            }
+        } else if ["-c", "-MD", "-MMD", "-MP"].contains(&arg.as_str())
+            || canonicalize_path(&arg, directory) == file
+        {
+            continue;
+        } else {
+            flags.cflags.push(arg);
+        }
+    }
+    (flags, output)
+}
+
+// Entry of a compilation database, with canonicalized paths.
+pub struct CompileCommand {
+    pub file: PathBuf,
+    pub output: Option<PathBuf>,
+    pub flags: CompileCommandFlags,
+}
+
+// Per-object flags read from the 'compile_commands.json' of a build directory.
+pub struct CompileCommands {
+    entries: Vec<CompileCommand>,
+    outputs: HashMap<PathBuf, usize>,
+    // Sources compiled more than once cannot identify an entry.
+    files: HashMap<PathBuf, Option<usize>>,
+    build_path: PathBuf,
+}
+
+impl CompileCommands {
+    pub fn new(build_path: &Path) -> Result<Self, String> {
+        let file_path = build_path.join(COMPILE_COMMANDS_FILE);
+        if !file_path.exists() {
+            return error!("{file_path:#?} not found");
+        }
+        let content = read_file(&file_path)?;
+        let entries = match parse_json(&content) {
+            Ok(JsonValue::Array(entries)) => entries,
+            Ok(_) => return error!("{file_path:#?} is not an array"),
+            Err(err) => return error!("Could not parse {file_path:#?}: {err}"),
+        };
+        let mut compile_commands = Self {
+            entries: Vec::new(),
+            outputs: HashMap::new(),
+            files: HashMap::new(),
+            build_path: PathBuf::from(build_path),
+        };
+        for entry in entries {
+            let JsonValue::Object(members) = entry else {
+                return error!("{file_path:#?} entries are not objects");
+            };
+            let mut directory = None;
+            let mut file = None;
+            let mut output = None;
+            let mut args = None;
+            for (key, value) in members {
+                match (key.as_str(), value) {
+                    ("directory", JsonValue::String(value)) => directory = Some(value),
+                    ("file", JsonValue::String(value)) => file = Some(value),
+                    ("output", JsonValue::String(value)) => output = Some(value),
+                    ("command", JsonValue::String(value)) => args = Some(split_command(&value)),
+                    ("arguments", JsonValue::Array(values)) => {
+                        args = Some(
+                            values
+                                .into_iter()
+                                .filter_map(|value| match value {
+                                    JsonValue::String(value) => Some(value),
+                                    _ => None,
+                                })
+                                .collect(),
+                        )
+                    }
+                    _ => (),
+                }
+            }
+            let (Some(directory), Some(file), Some(args)) = (directory, file, args) else {
+                return error!("{file_path:#?} entry without 'directory', 'file' or command");
+            };
+            let directory = canonicalize_path(directory, build_path);
+            let file = canonicalize_path(file, &directory);
+            let index = compile_commands.entries.len();
+            let (flags, command_output) = get_flags(args, &file, &directory);
+            let output = output
+                .map(|output| canonicalize_path(output, &directory))
+                .or(command_output);
+            if let Some(output) = &output {
+                compile_commands.outputs.insert(output.clone(), index);
+            }
+            compile_commands
+                .files
+                .entry(file.clone())
+                .and_modify(|index| *index = None)
+                .or_insert(Some(index));
+            compile_commands.entries.push(CompileCommand {
+                file,
+                output,
+                flags,
+            });
+        }
+        Ok(compile_commands)
+    }
+    // Entries are identified by their output when the generator provides it, by their source
+    // otherwise.
+    pub fn get(&self, output: &Path, source: &Path) -> Option<&CompileCommandFlags> {
+        let index = match self
+            .outputs
+            .get(&canonicalize_path(output, &self.build_path))
+        {
+            Some(index) => *index,
+            None => {
+                (*self
+                    .files
+                    .get(&canonicalize_path(source, &self.build_path))?)?
+            }
+        };
+        Some(&self.entries[index].flags)
+    }
+    pub fn get_commands(&self) -> &Vec<CompileCommand> {
+        &self.entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_json_surrogate_pairs() {
+        let Ok(JsonValue::String(string)) = parse_json("\"\\ud83d\\ude00\"") else {
+            panic!("Could not parse surrogate pair");
+        };
+        assert_eq!(string, "\u{1F600}");
+        assert!(parse_json("\"\\ud83d\\u0041\"").is_err());
+        assert!(parse_json("\"\\ud83d\\ud83d\"").is_err());
+        assert!(parse_json("\"\\ud83d\"").is_err());
+    }
+
+    #[test]
+    fn get_flags_with_argument() {
+        let (flags, output) = get_flags(
+            split_command(
+                "clang -D FOO -isystem inc -include config.h -Xclang -fno-pch-timestamp \
+                 -MD -MF foo.o.d -o foo.o -c foo.c",
+            ),
+            Path::new("/build/foo.c"),
+            Path::new("/build"),
+        );
+        assert_eq!(flags.defines, vec!["FOO"]);
+        assert_eq!(flags.includes, vec![PathBuf::from("/build/inc")]);
+        assert_eq!(
+            flags.cflags,
+            vec!["-include config.h", "-Xclang -fno-pch-timestamp"]
+        );
+        assert_eq!(output, Some(PathBuf::from("/build/foo.o")));
+    }
+}
@@ -3,7 +3,9 @@
 
 use std::collections::VecDeque;
 use std::env;
+use std::hash::{DefaultHasher, Hash, Hasher};
 
+use crate::compile_commands::*;
 use crate::project::*;
 use crate::utils::*;
 
@@ -11,6 +13,7 @@ use crate::utils::*;
 pub struct Context {
     pub projects_to_generate: VecDeque<ProjectId>,
     temp_path: PathBuf,
+    ninja_cache_path: Option<PathBuf>,
     clean_gen_ninja: bool,
     n2s_path: PathBuf,
     android_path: Option<PathBuf>,
@@ -21,6 +24,7 @@ pub struct Context {
     pub skip_build: bool,
     pub copy_to_aosp: bool,
     pub wildcardize_paths: bool,
+    pub compile_commands: Option<CompileCommandsMode>,
 }
 
 const AOSP_PATH: &str = "--aosp-path";
@@ -37,9 +41,12 @@ const SKIP_BUILD: &str = "--skip-build";
 const SKIP_BUILD_SHORT: &str = "-s";
 const SKIP_GEN_NINJA: &str = "--skip-gen-ninja";
 const SKIP_GEN_NINJA_SHORT: &str = "-S";
+const COMPILE_COMMANDS: &str = "--compile-commands";
+const COMPILE_COMMANDS_SHORT: &str = "-d";
 const HELP: &str = "--help";
 const HELP_SHORT: &str = "-h";
 
+const NINJA_CACHE_FOLDER: &str = "ninja-cache";
 const TESTS_FOLDER: &str = "tests";
 const SCRIPTS_FOLDER: &str = "scripts";
 const TARGET_FOLDER: &str = "target";
@@ -53,7 +60,17 @@ impl Context {
         Ok(path)
     }
 
+    pub fn get_ninja_cache_path(&self, build_path: &Path) -> Option<PathBuf> {
+        let ninja_cache_path = self.ninja_cache_path.as_ref()?;
+        let mut hasher = DefaultHasher::new();
+        build_path.hash(&mut hasher);
+        Some(ninja_cache_path.join(format!("{:016x}", hasher.finish())))
+    }
+
     pub fn get_test_path(&self, project: &dyn Project) -> PathBuf {
+        if let Some(unittest_path) = &self.unittest_path {
+            return unittest_path.clone();
+        }
         self.n2s_path.join(TESTS_FOLDER).join(project.get_name())
     }
 
@@ -130,6 +147,14 @@ impl Context {
                     ctx.skip_build = true
                 }
                 SKIP_BUILD_SHORT | SKIP_BUILD => ctx.skip_build = true,
+                COMPILE_COMMANDS_SHORT | COMPILE_COMMANDS => {
+                    ctx.compile_commands = match iter.next() {
+                        Some(mode) => Some(CompileCommandsMode::new(mode)?),
+                        None => {
+                            return error!("'check' or 'override' missing for {COMPILE_COMMANDS}")
+                        }
+                    }
+                }
                 COPY_TO_AOSP_SHORT | COPY_TO_AOSP => {
                     ctx.copy_to_aosp = true;
                     ctx.wildcardize_paths = true;
@@ -156,6 +181,7 @@ OPTIONS:
 {COPY_TO_AOSP_SHORT}, {COPY_TO_AOSP}\t\tCopy generated Soong files into the Android tree
 {SKIP_BUILD_SHORT}, {SKIP_BUILD}\t\tSkip build step
 {SKIP_GEN_NINJA_SHORT}, {SKIP_GEN_NINJA}\t\tSkip generation of Ninja files
+{COMPILE_COMMANDS_SHORT}, {COMPILE_COMMANDS} <mode>\tCheck ('check') or override ('override') compile flags with compile_commands.json
 {HELP_SHORT}, {HELP}\t\t\tDisplay the help and exit
 ",
                         projects_help.concat()
@@ -186,6 +212,7 @@ OPTIONS:
         if create_dir(&ctx.temp_path)? {
             print_info!("{0:#?} created", ctx.temp_path);
         }
+        ctx.ninja_cache_path = Some(ctx.temp_path.join(NINJA_CACHE_FOLDER));
         // TEST_PATH
         match env::current_exe() {
             Ok(exe_path) => {
@@ -1,3 +1,4 @@
+pub mod compile_commands;
 pub mod context;
 pub mod ninja_parser;
 pub mod ninja_target;
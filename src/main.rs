@@ -5,6 +5,7 @@ use std::collections::HashSet;
 
 use libloading::Library;
 
+mod compile_commands;
 mod context;
 mod ninja_parser;
 mod ninja_target;
@@ -1,296 +1,646 @@
 // Copyright 2024 ninja-to-soong authors
 // SPDX-License-Identifier: Apache-2.0
 
-use std::collections::HashMap;
-use std::str;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
 
+use crate::context::*;
 use crate::ninja_target::*;
 use crate::utils::*;
 
-fn parse_output_section(section: &str) -> Result<(Vec<PathBuf>, Vec<PathBuf>), String> {
-    let mut split = section.split("|");
-    if split.clone().count() < 1 {
-        return error!("parse_output_section failed: '{section}'");
-    }
-    let split_outputs = split.nth(0).unwrap().trim().split(" ");
-    let outputs = split_outputs
-        .map(|output| PathBuf::from(output.trim()))
-        .collect();
-    let mut implicit_outputs = Vec::new();
-    if let Some(implicit_outs) = split.next() {
-        for implicit_output in implicit_outs.trim().split(" ") {
-            implicit_outputs.push(PathBuf::from(implicit_output.trim()));
+mod cache;
+mod dyndep;
+mod error;
+mod lexer;
+mod scope;
+
+use cache::*;
+use dyndep::*;
+pub use error::*;
+use lexer::*;
+use scope::*;
+
+const CONSOLE_POOL: &str = "console";
+
+// State shared by every file of the manifest, whatever their scope. Subninja files are parsed with
+// their own state, merged into the one of their parent afterward.
+#[derive(Default)]
+struct NinjaState {
+    edges: Vec<NinjaEdge>,
+    defaults: Vec<PathBuf>,
+    pools: HashMap<String, NinjaLocation>,
+    // Pools are global, they can only be checked once every file has been parsed.
+    used_pools: HashMap<String, NinjaLocation>,
+    outputs: Vec<PathBuf>,
+    deps: HashSet<PathBuf>,
+    globals: Vec<(PathBuf, NinjaBindings)>,
+    files: Vec<(PathBuf, Option<u64>)>,
+}
+
+impl NinjaState {
+    fn merge(&mut self, state: NinjaState) -> Result<(), NinjaParseError> {
+        let globals_offset = self.globals.len();
+        self.edges.extend(state.edges.into_iter().map(|mut edge| {
+            edge.globals += globals_offset;
+            edge
+        }));
+        self.globals.extend(state.globals);
+        self.defaults.extend(state.defaults);
+        for (pool, location) in state.pools {
+            if self.pools.contains_key(&pool) {
+                return Err(NinjaParseError::DuplicatePool { location, pool });
+            }
+            self.pools.insert(pool, location);
+        }
+        for (pool, location) in state.used_pools {
+            self.used_pools.entry(pool).or_insert(location);
         }
+        self.outputs.extend(state.outputs);
+        self.deps.extend(state.deps);
+        self.files.extend(state.files);
+        Ok(())
     }
-    Ok((outputs, implicit_outputs))
 }
 
-fn parse_input_and_rule_section(section: &str) -> Result<(String, Vec<PathBuf>), String> {
-    let mut split = section.trim().split(" ");
-    if split.clone().count() < 1 {
-        return error!("parse_input_and_rule_section failed: '{section}'");
-    }
-    let rule = String::from(split.nth(0).unwrap());
-    let inputs = split
-        .map(|input| PathBuf::from(input.trim()))
-        .collect::<Vec<PathBuf>>();
-    Ok((rule, inputs))
+// Edge of the Ninja graph, before being turned into a target of a given generator.
+struct NinjaEdge {
+    common: NinjaTargetCommon,
+    rule_cmd: Option<NinjaRuleCmd>,
+    // Index of the file declaring the edge, with its variables, in 'NinjaGraph::globals'.
+    globals: usize,
+    // Dyndep file of the edge, already merged into 'common' once the graph is complete.
+    dyndep: Option<PathBuf>,
 }
 
-fn parse_input_and_deps_section(
-    section: &str,
-) -> Result<(String, Vec<PathBuf>, Vec<PathBuf>), String> {
-    let mut split = section.split("|");
-    let split_count = split.clone().count();
-    if split_count != 1 && split_count != 2 {
-        return error!("parse_input_and_deps_section failed: '{section}'");
+impl NinjaEdge {
+    fn new(common: NinjaTargetCommon, rule_cmd: Option<NinjaRuleCmd>) -> Self {
+        Self {
+            common,
+            rule_cmd,
+            globals: 0,
+            dyndep: None,
+        }
     }
+}
 
-    let (rule, inputs) = parse_input_and_rule_section(split.nth(0).unwrap())?;
+// Result of the parsing of a build directory, whatever the generator of the targets.
+struct NinjaGraph {
+    edges: Vec<NinjaEdge>,
+    // Ninja files declaring edges, relative to the build directory, with their variables.
+    globals: Vec<(PathBuf, NinjaBindings)>,
+    defaults: Vec<PathBuf>,
+    // Every Ninja file read with the hash of its content, 'None' for missing dyndep files.
+    files: Vec<(PathBuf, Option<u64>)>,
+}
 
-    let mut implicit_deps = Vec::new();
-    if let Some(implicit_dependencies) = split.next() {
-        for implicit_dep in implicit_dependencies.trim().split(" ") {
-            implicit_deps.push(PathBuf::from(implicit_dep.trim()));
+impl NinjaGraph {
+    fn into_targets<T, F>(self, new_target: F) -> Vec<T>
+    where
+        T: NinjaTarget,
+        F: Fn(NinjaTargetCommon) -> T,
+    {
+        let mut targets = Vec::new();
+        for edge in self.edges {
+            let mut target = new_target(edge.common);
+            if let Some(rule_cmd) = edge.rule_cmd {
+                target.set_rule(rule_cmd);
+            }
+            let (file_path, globals) = &self.globals[edge.globals];
+            target.set_ninja_file(file_path);
+            target.set_globals(globals.clone());
+            targets.push(target);
         }
+        targets
     }
-    Ok((rule, inputs, implicit_deps))
 }
 
-fn parse_input_section(
-    section: &str,
-) -> Result<(String, Vec<PathBuf>, Vec<PathBuf>, Vec<PathBuf>), String> {
-    let mut split = section.split("||");
-    let split_count = split.clone().count();
-    if split_count != 1 && split_count != 2 {
-        return error!("parse_input_section failed: '{section}'");
-    }
-
-    let (rule, inputs, implicit_deps) = parse_input_and_deps_section(split.nth(0).unwrap())?;
+#[derive(Default)]
+struct NinjaBuildLine<'a> {
+    outputs: Vec<&'a str>,
+    implicit_outputs: Vec<&'a str>,
+    rule: &'a str,
+    inputs: Vec<&'a str>,
+    implicit_deps: Vec<&'a str>,
+    order_only_deps: Vec<&'a str>,
+}
 
-    let mut order_only_deps = Vec::new();
-    if let Some(order_only_dependencies) = split.next() {
-        for dep in order_only_dependencies.trim().split(" ") {
-            order_only_deps.push(PathBuf::from(dep.trim()));
+// Build line, with the 'build' keyword, to report errors at their offset in the statement.
+fn parse_build_line(line: &str) -> Result<NinjaBuildLine<'_>, NinjaSyntaxError> {
+    let mut build_line = NinjaBuildLine::default();
+    let mut section = &mut build_line.outputs;
+    let mut tokens = tokenize(line)?.into_iter().skip(1);
+    while let Some(token) = tokens.next() {
+        section = match token {
+            NinjaToken::Path(path) => {
+                section.push(path);
+                continue;
+            }
+            NinjaToken::Pipe if build_line.rule.is_empty() => &mut build_line.implicit_outputs,
+            NinjaToken::Colon if build_line.rule.is_empty() => {
+                let Some(NinjaToken::Path(rule)) = tokens.next() else {
+                    return error!("parse_build_line failed, missing rule: '{line}'")
+                        .map_err(NinjaSyntaxError::from);
+                };
+                build_line.rule = rule;
+                &mut build_line.inputs
+            }
+            NinjaToken::Pipe => &mut build_line.implicit_deps,
+            NinjaToken::PipePipe => &mut build_line.order_only_deps,
+            // Validations are not needed to generate Soong modules.
+            NinjaToken::PipeAt => break,
+            NinjaToken::Colon => {
+                return error!("parse_build_line failed: '{line}'").map_err(NinjaSyntaxError::from)
+            }
         }
     }
-    Ok((rule, inputs, implicit_deps, order_only_deps))
+    if build_line.rule.is_empty() || build_line.outputs.is_empty() {
+        return error!("parse_build_line failed: '{line}'").map_err(NinjaSyntaxError::from);
+    }
+    Ok(build_line)
 }
 
-fn find_column_index(line: &str) -> Option<usize> {
-    let Some(index) = line.find(":") else {
-        return None;
-    };
-    if line.as_bytes()[0..index].ends_with("$".as_bytes()) {
-        if let Some(sub_index) =
-            find_column_index(str::from_utf8(&line.as_bytes()[index + 1..]).unwrap())
-        {
-            return Some(index + 1 + sub_index);
+fn evaluate_paths(paths: &[&str], edge_scope: &NinjaEdgeScope) -> Result<Vec<PathBuf>, String> {
+    let mut evaluated_paths = Vec::new();
+    for path in paths {
+        evaluated_paths.push(PathBuf::from(if path.contains('$') {
+            edge_scope.evaluate(path)?
         } else {
-            return None;
-        }
+            String::from(*path)
+        }));
     }
-    Some(index)
-}
-
-fn split_output_and_input_sections(line: &str) -> Result<(&str, &str), String> {
-    let Some(index) = find_column_index(line) else {
-        return error!("split_output_and_input_sections failed: '{line}'");
-    };
-    Ok((
-        str::from_utf8(&line.as_bytes()[0..index]).unwrap(),
-        str::from_utf8(&line.as_bytes()[index + 1..]).unwrap(),
-    ))
+    Ok(evaluated_paths)
 }
 
-fn parse_key_value(line: &str) -> Result<(String, String), String> {
+fn parse_key_value(line: &str) -> Result<(&str, &str), String> {
     let Some(split) = line.split_once("=") else {
         return error!("parse_key_value failed: '{line}'");
     };
-    Ok((String::from(split.0.trim()), String::from(split.1.trim())))
+    Ok((split.0.trim(), split.1.trim()))
 }
 
-fn get_subtarget<T>(
-    rule: &String,
-    outputs: &mut Vec<PathBuf>,
-    variables: &mut HashMap<String, String>,
-) -> Result<Vec<T>, String>
+fn parse_bindings<F>(lines: &mut NinjaLines, mut add_binding: F) -> Result<(), String>
 where
-    T: NinjaTarget,
+    F: FnMut(&str, &str) -> Result<(), String>,
 {
-    let mut targets = Vec::new();
-    if !variables.contains_key("COMMAND") {
-        return Ok(targets);
-    }
-
-    let rename_output = |output: &PathBuf| Path::new("n2s").join(output.clone());
-    for output in &outputs.clone() {
-        let input = rename_output(output);
-        let mut variables = variables.clone();
-        *variables.get_mut("COMMAND").unwrap() = String::from("cp $(in) $(out)");
-        targets.push(T::new(NinjaTargetCommon {
-            rule: rule.clone(),
-            outputs: vec![output.clone()],
-            implicit_outputs: Vec::new(),
-            inputs: vec![input],
-            implicit_deps: Vec::new(),
-            order_only_deps: Vec::new(),
-            variables,
-        }));
-    }
-    let old_outputs = outputs.clone();
-    outputs
-        .into_iter()
-        .for_each(|output| *output = rename_output(&output));
-    let cmd = variables.get_mut("COMMAND").unwrap();
-    for output_id in 0..old_outputs.len() {
-        *cmd = cmd.replace(
-            &path_to_string(&old_outputs[output_id]),
-            &path_to_string(&outputs[output_id]),
-        );
+    while let Some(line) = lines.next_if(|line| line.starts_with(" ")) {
+        let (key, value) = parse_key_value(&line)?;
+        add_binding(key, value)?;
     }
-
-    Ok(targets)
+    Ok(())
 }
 
-fn parse_build_target<T>(line: &str, mut lines: str::Lines) -> Result<Vec<T>, String>
-where
-    T: NinjaTarget,
-{
-    let Some(line_stripped) = line.strip_prefix("build ") else {
-        return error!("parse_build_target failed: '{line}'");
-    };
-
-    let (output_section, input_section) = split_output_and_input_sections(line_stripped)?;
+fn parse_build_target(
+    line: &str,
+    lines: &mut NinjaLines,
+    scope: &NinjaScope,
+    state: &mut NinjaState,
+) -> Result<(NinjaEdge, Option<String>), NinjaSyntaxError> {
+    let build_line = parse_build_line(line)?;
+    let rule = String::from(build_line.rule);
 
-    let (mut outputs, implicit_outputs) = parse_output_section(output_section)?;
-    let (rule, inputs, implicit_deps, order_only_deps) = parse_input_section(input_section)?;
-
-    let mut variables: HashMap<String, String> = HashMap::new();
-    while let Some(next_line) = lines.next() {
-        if !next_line.starts_with(" ") {
-            break;
-        }
-        let (key, value) = parse_key_value(next_line)?;
-        variables.insert(key, value);
-    }
+    let mut edge_scope = NinjaEdgeScope::new(&rule, scope);
+    parse_bindings(lines, |key, value| {
+        edge_scope.add_binding(String::from(key), value)
+    })?;
+    let outputs = evaluate_paths(&build_line.outputs, &edge_scope)?;
+    let implicit_outputs = evaluate_paths(&build_line.implicit_outputs, &edge_scope)?;
+    let inputs = evaluate_paths(&build_line.inputs, &edge_scope)?;
+    let implicit_deps = evaluate_paths(&build_line.implicit_deps, &edge_scope)?;
+    let order_only_deps = evaluate_paths(&build_line.order_only_deps, &edge_scope)?;
+    state.outputs.extend(outputs.iter().cloned());
+    state.deps.extend(inputs.iter().cloned());
+    state.deps.extend(implicit_deps.iter().cloned());
+    state.deps.extend(order_only_deps.iter().cloned());
+    edge_scope.set_paths(&inputs, &outputs);
+    let pool = edge_scope
+        .get_binding("pool")?
+        .filter(|pool| !pool.is_empty() && pool != CONSOLE_POOL);
+    let rule_cmd = if edge_scope.has_rule() {
+        let Some(command) = edge_scope.get_binding("command")? else {
+            return error!("parse_build_target failed: no command for '{rule}'")
+                .map_err(NinjaSyntaxError::from);
+        };
+        let rspfile = edge_scope.get_binding("rspfile")?;
+        let rspfile_content = edge_scope.get_binding("rspfile_content")?;
+        Some(NinjaRuleCmd {
+            command,
+            rsp_info: match (rspfile, rspfile_content) {
+                (Some(rspfile), Some(rspfile_content)) => Some((rspfile, rspfile_content)),
+                _ => None,
+            },
+        })
+    } else {
+        None
+    };
+    let dyndep = edge_scope.get_binding("dyndep")?;
+    let variables = edge_scope.bindings;
 
-    let mut targets = Vec::new();
-    if outputs.len() > 1 {
-        targets.extend(get_subtarget(&rule, &mut outputs, &mut variables)?)
-    }
-    targets.push(T::new(NinjaTargetCommon {
-        rule,
-        outputs,
-        implicit_outputs,
-        inputs,
-        implicit_deps,
-        order_only_deps,
-        variables,
-    }));
-    Ok(targets)
+    let mut target = NinjaEdge::new(
+        NinjaTargetCommon {
+            rule,
+            outputs,
+            implicit_outputs,
+            inputs,
+            implicit_deps,
+            order_only_deps,
+            variables,
+        },
+        rule_cmd,
+    );
+    target.dyndep = dyndep.map(PathBuf::from);
+    Ok((target, pool))
 }
 
-fn parse_ninja_rule(line: &str, mut lines: str::Lines) -> Result<(String, NinjaRuleCmd), String> {
+fn parse_ninja_rule(line: &str, lines: &mut NinjaLines) -> Result<(String, NinjaBindings), String> {
     let Some(rule) = line.strip_prefix("rule ") else {
         return error!("parse_ninja_rule failed: '{line}'");
     };
-    let mut command = None;
-    let mut rspfile = None;
-    let mut rspfile_content = None;
-    while let Some(next_line) = lines.next() {
-        if !next_line.starts_with(" ") {
-            break;
-        }
-        let (key, value) = parse_key_value(next_line)?;
-        match key.as_str() {
-            "command" => command = Some(value),
-            "rspfile" => rspfile = Some(value),
-            "rspfile_content" => rspfile_content = Some(value),
-            _ => (),
-        }
-    }
-    if command.is_none() {
+    let mut bindings = NinjaBindings::new();
+    parse_bindings(lines, |key, value| {
+        bindings.insert(String::from(key), String::from(value));
+        Ok(())
+    })?;
+    if !bindings.contains_key("command") {
         return error!("parse_ninja_rule failed");
     }
-    return Ok((
-        String::from(rule),
-        NinjaRuleCmd {
-            command: command.unwrap(),
-            rsp_info: if rspfile.is_some() && rspfile_content.is_some() {
-                Some((rspfile.unwrap(), rspfile_content.unwrap()))
-            } else {
-                None
-            },
-        },
-    ));
+    Ok((String::from(rule.trim()), bindings))
 }
 
-fn parse_subninja_file<T>(line: &str, dir_path: &Path) -> Result<(Vec<T>, NinjaRulesMap), String>
-where
-    T: NinjaTarget,
-{
-    let mut split = line.split(" ");
-    let split_count = split.clone().count();
-    if split_count != 2 {
-        return error!("parse_subninja_file failed: '{line}'");
+fn parse_ninja_pool(line: &str, lines: &mut NinjaLines) -> Result<(String, usize), String> {
+    let Some(pool) = line.strip_prefix("pool ") else {
+        return error!("parse_ninja_pool failed: '{line}'");
+    };
+    let mut depth = None;
+    parse_bindings(lines, |key, value| {
+        if key != "depth" {
+            return error!("parse_ninja_pool failed, unexpected variable '{key}': '{line}'");
+        }
+        let Ok(value) = value.parse::<usize>() else {
+            return error!("parse_ninja_pool failed, invalid depth '{value}': '{line}'");
+        };
+        depth = Some(value);
+        Ok(())
+    })?;
+    let Some(depth) = depth else {
+        return error!("parse_ninja_pool failed, missing depth: '{line}'");
+    };
+    Ok((String::from(pool.trim()), depth))
+}
+
+fn parse_ninja_default(line: &str, scope: &NinjaScope) -> Result<Vec<PathBuf>, NinjaSyntaxError> {
+    let mut defaults = Vec::new();
+    for token in tokenize(line)?.into_iter().skip(1) {
+        let NinjaToken::Path(path) = token else {
+            return error!("parse_ninja_default failed: '{line}'").map_err(NinjaSyntaxError::from);
+        };
+        defaults.push(PathBuf::from(scope.evaluate(path)?));
+    }
+    if defaults.is_empty() {
+        return error!("parse_ninja_default failed, expected target name: '{line}'")
+            .map_err(NinjaSyntaxError::from);
     }
-    parse_ninja_file(dir_path.join(split.nth(1).unwrap()), dir_path)
+    Ok(defaults)
+}
+
+fn get_subninja_file(
+    line: &str,
+    dir_path: &Path,
+    scope: &NinjaScope,
+) -> Result<PathBuf, NinjaSyntaxError> {
+    let mut tokens = tokenize(line)?.into_iter().skip(1);
+    let (Some(NinjaToken::Path(file)), None) = (tokens.next(), tokens.next()) else {
+        return error!("get_subninja_file failed: '{line}'").map_err(NinjaSyntaxError::from);
+    };
+    Ok(dir_path.join(scope.evaluate(file)?))
 }
 
-fn parse_ninja_file<T>(
+fn parse_ninja_file_in_scope(
     file_path: PathBuf,
     build_path: &Path,
-) -> Result<(Vec<T>, NinjaRulesMap), String>
-where
-    T: NinjaTarget,
-{
-    let mut all_targets = Vec::new();
-    let mut targets: Vec<T> = Vec::new();
-    let mut globals = HashMap::new();
-    let mut all_rules = HashMap::new();
-
-    let file = read_file(&file_path)?.replace("$\n", " ");
+    scope: &mut NinjaScope,
+    state: &mut NinjaState,
+    targets: &mut Vec<NinjaEdge>,
+    subninjas: &mut Vec<(PathBuf, usize)>,
+) -> Result<(), NinjaParseError> {
+    let file = match read_file(&file_path) {
+        Ok(file) => file,
+        Err(message) => {
+            return Err(NinjaParseError::Read {
+                file: file_path,
+                message,
+            })
+        }
+    };
+    state
+        .files
+        .push((file_path.clone(), Some(hash_content(&file))));
 
-    let mut lines = file.lines();
+    let mut lines = NinjaLines::new(&file);
     while let Some(line) = lines.next() {
-        if line.is_empty()
-            || line.starts_with("default ")
-            || line.starts_with("pool ")
-            || line.starts_with("#")
-            || line.starts_with(" ")
-        {
+        let line = line.as_ref();
+        let position = lines.get_position();
+        let location = |lines: &NinjaLines, offset| {
+            let (line, column) = lines.get_line_column(position, offset);
+            NinjaLocation {
+                file: file_path.clone(),
+                line,
+                column,
+                snippet: String::from(lines.get_raw_lines(position)),
+            }
+        };
+        let syntax_error = |lines: &NinjaLines, err: NinjaSyntaxError| NinjaParseError::Syntax {
+            location: location(lines, err.offset),
+            message: err.message,
+        };
+        if line.is_empty() || line.starts_with("#") || line.starts_with(" ") {
             continue;
+        } else if line.starts_with("pool ") {
+            let (pool, _depth) = parse_ninja_pool(line, &mut lines)
+                .map_err(|err| syntax_error(&lines, err.into()))?;
+            if state.pools.contains_key(&pool) {
+                return Err(NinjaParseError::DuplicatePool {
+                    location: location(&lines, 0),
+                    pool,
+                });
+            }
+            state.pools.insert(pool, location(&lines, 0));
+        } else if line.starts_with("default ") {
+            let defaults =
+                parse_ninja_default(line, scope).map_err(|err| syntax_error(&lines, err))?;
+            state.defaults.extend(defaults);
         } else if line.starts_with("rule ") {
-            let (rule, rule_command) = parse_ninja_rule(line, lines.clone())?;
-            all_rules.insert(rule, rule_command);
+            let (rule, bindings) = parse_ninja_rule(line, &mut lines)
+                .map_err(|err| syntax_error(&lines, err.into()))?;
+            scope.add_rule(rule, bindings);
         } else if line.starts_with("build ") {
-            targets.extend(parse_build_target(line, lines.clone())?);
-        } else if line.starts_with("subninja ") || line.starts_with("include ") {
-            let (subtargets, rules) = parse_subninja_file(line, build_path)?;
-            all_targets.extend(subtargets);
-            all_rules.extend(rules);
+            let (edge, pool) = parse_build_target(line, &mut lines, scope, state)
+                .map_err(|err| syntax_error(&lines, err))?;
+            targets.push(edge);
+            if let Some(pool) = pool {
+                state
+                    .used_pools
+                    .entry(pool)
+                    .or_insert_with(|| location(&lines, 0));
+            }
+        } else if line.starts_with("include ") {
+            let file_path = get_subninja_file(line, build_path, scope)
+                .map_err(|err| syntax_error(&lines, err))?;
+            parse_ninja_file_in_scope(file_path, build_path, scope, state, targets, subninjas)?;
+        } else if line.starts_with("subninja ") {
+            let file_path = get_subninja_file(line, build_path, scope)
+                .map_err(|err| syntax_error(&lines, err))?;
+            subninjas.push((file_path, scope.get_version()));
         } else {
-            let (key, value) = parse_key_value(line)?;
-            globals.insert(key, value);
+            parse_key_value(line)
+                .and_then(|(key, value)| scope.add_variable(String::from(key), value))
+                .map_err(|err| syntax_error(&lines, err.into()))?;
         }
     }
+    Ok(())
+}
+
+// Subninja files are independent from each other, they are parsed concurrently. Their states are
+// returned in the order of the subninja statements to keep the result deterministic.
+fn parse_subninja_files(
+    subninjas: Vec<(PathBuf, usize)>,
+    build_path: &Path,
+    scope: &Arc<NinjaScope>,
+) -> Result<Vec<NinjaState>, NinjaParseError> {
+    let parse_subninja_file = |(file_path, version): &(PathBuf, usize)| {
+        parse_ninja_file(
+            file_path.clone(),
+            build_path,
+            Some((scope.clone(), *version)),
+        )
+    };
+    if subninjas.len() <= 1 {
+        return subninjas.iter().map(parse_subninja_file).collect();
+    }
+    let workers = thread::available_parallelism()
+        .map_or(1, |workers| workers.get())
+        .min(subninjas.len());
+    if workers <= 1 {
+        return subninjas.iter().map(parse_subninja_file).collect();
+    }
+    let next_subninja = AtomicUsize::new(0);
+    let states = subninjas
+        .iter()
+        .map(|_| Mutex::new(None))
+        .collect::<Vec<_>>();
+    thread::scope(|threads| {
+        for _ in 0..workers {
+            threads.spawn(|| loop {
+                let index = next_subninja.fetch_add(1, Ordering::Relaxed);
+                let Some(subninja) = subninjas.get(index) else {
+                    break;
+                };
+                *states[index].lock().unwrap() = Some(parse_subninja_file(subninja));
+            });
+        }
+    });
+    states
+        .into_iter()
+        .map(|state| state.into_inner().unwrap().unwrap())
+        .collect()
+}
+
+fn parse_ninja_file(
+    file_path: PathBuf,
+    build_path: &Path,
+    parent: Option<(Arc<NinjaScope>, usize)>,
+) -> Result<NinjaState, NinjaParseError> {
+    let mut state = NinjaState::default();
+    let mut targets = Vec::new();
+    let mut subninjas = Vec::new();
+    let mut scope = NinjaScope::new(parent);
+    let relative_file_path = strip_prefix(&file_path, build_path);
+    parse_ninja_file_in_scope(
+        file_path,
+        build_path,
+        &mut scope,
+        &mut state,
+        &mut targets,
+        &mut subninjas,
+    )?;
+    let globals = state.globals.len();
+    state
+        .globals
+        .push((relative_file_path, scope.get_variables()));
     for target in &mut targets {
-        target.set_globals(globals.clone());
+        target.globals = globals;
+    }
+    let scope = Arc::new(scope);
+    for subninja_state in parse_subninja_files(subninjas, build_path, &scope)? {
+        state.merge(subninja_state)?;
     }
-    all_targets.extend(targets);
+    state.edges.extend(targets);
 
-    Ok((all_targets, all_rules))
+    Ok(state)
 }
 
-pub fn parse_build_ninja<T>(build_path: &Path) -> Result<Vec<T>, String>
-where
-    T: NinjaTarget,
-{
-    let file_path = build_path.join("build.ninja");
-    let (mut targets, rules) = parse_ninja_file::<T>(file_path, build_path)?;
-    for target in &mut targets {
-        target.set_rule(&rules);
+// Without any 'default' statement, Ninja builds every target that is not an input of another one.
+fn get_root_targets(state: &NinjaState) -> Vec<PathBuf> {
+    state
+        .outputs
+        .iter()
+        .filter(|output| !state.deps.contains(*output))
+        .cloned()
+        .collect()
+}
+
+fn parse_ninja_graph(build_path: &Path) -> Result<NinjaGraph, NinjaParseError> {
+    let mut state = parse_ninja_file(build_path.join("build.ninja"), build_path, None)?;
+    load_dyndep_files(build_path, &mut state)?;
+    for (pool, location) in &state.used_pools {
+        if !state.pools.contains_key(pool) {
+            return Err(NinjaParseError::UnknownPool {
+                location: location.clone(),
+                pool: pool.clone(),
+            });
+        }
+    }
+    let defaults = if state.defaults.is_empty() {
+        get_root_targets(&state)
+    } else {
+        state.defaults
+    };
+    Ok(NinjaGraph {
+        edges: state.edges,
+        globals: state.globals,
+        defaults,
+        files: state.files,
+    })
+}
+
+// Synthesized edges are not cached, they are as cheap to get again as the cache would be to read.
+fn synthesize_ninja_graph(
+    build_path: &Path,
+    synthesize_edges: NinjaEdgesSynthesizer,
+) -> Result<NinjaGraph, NinjaParseError> {
+    let edges = match synthesize_edges(build_path) {
+        Ok(edges) => edges,
+        Err(message) => {
+            return Err(NinjaParseError::Read {
+                file: PathBuf::from(build_path),
+                message,
+            })
+        }
+    };
+    let mut state = NinjaState::default();
+    for common in edges {
+        state.outputs.extend(common.outputs.iter().cloned());
+        state.deps.extend(
+            common
+                .inputs
+                .iter()
+                .chain(&common.implicit_deps)
+                .chain(&common.order_only_deps)
+                .cloned(),
+        );
+        state.edges.push(NinjaEdge::new(common, None));
     }
-    Ok(targets)
+    Ok(NinjaGraph {
+        defaults: get_root_targets(&state),
+        edges: state.edges,
+        globals: vec![(PathBuf::new(), NinjaBindings::new())],
+        files: Vec::new(),
+    })
+}
+
+// Variables loaded from outside of the Ninja files are added after the cache, which only tracks
+// Ninja files.
+fn add_edges_variables(
+    graph: &mut NinjaGraph,
+    build_path: &Path,
+    load_edges_variables: NinjaEdgesVariablesLoader,
+) -> Result<(), NinjaParseError> {
+    let edges_variables = match load_edges_variables(build_path) {
+        Ok(edges_variables) => edges_variables,
+        Err(message) => {
+            return Err(NinjaParseError::Read {
+                file: PathBuf::from(build_path),
+                message,
+            })
+        }
+    };
+    for edge in &mut graph.edges {
+        for output in &edge.common.outputs {
+            if let Some(variables) = edges_variables.get(output) {
+                edge.common.variables.extend(variables.clone());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn get_ninja_graph(
+    build_path: &Path,
+    ctx: &Context,
+    synthesize_edges: Option<NinjaEdgesSynthesizer>,
+    load_edges_variables: Option<NinjaEdgesVariablesLoader>,
+) -> Result<NinjaGraph, NinjaParseError> {
+    let mut graph = read_ninja_graph(build_path, ctx, synthesize_edges)?;
+    if let Some(load_edges_variables) = load_edges_variables {
+        add_edges_variables(&mut graph, build_path, load_edges_variables)?;
+    }
+    Ok(graph)
+}
+
+fn read_ninja_graph(
+    build_path: &Path,
+    ctx: &Context,
+    synthesize_edges: Option<NinjaEdgesSynthesizer>,
+) -> Result<NinjaGraph, NinjaParseError> {
+    if let Some(synthesize_edges) = synthesize_edges {
+        return synthesize_ninja_graph(build_path, synthesize_edges);
+    }
+    Ok(match ctx.get_ninja_cache_path(build_path) {
+        Some(cache_path) => match read_cache(&cache_path) {
+            Some(graph) => {
+                print_verbose!("{cache_path:#?} used");
+                graph
+            }
+            None => {
+                let graph = parse_ninja_graph(build_path)?;
+                if let Err(message) = write_cache(&cache_path, &graph) {
+                    return Err(NinjaParseError::Cache {
+                        file: cache_path,
+                        message,
+                    });
+                }
+                graph
+            }
+        },
+        None => parse_ninja_graph(build_path)?,
+    })
+}
+
+// Targets of a build file, with its 'default' targets.
+pub type NinjaTargetsWithDefaults<T> = (Vec<T>, Vec<PathBuf>);
+
+// Targets are created by the Ninja generator registered under 'generator' in 'NINJA_GENERATORS'.
+pub fn parse_build_ninja_with_defaults(
+    generator: &str,
+    build_path: &Path,
+    ctx: &Context,
+) -> Result<NinjaTargetsWithDefaults<Box<dyn NinjaTarget>>, String> {
+    let generator = get_ninja_generator(generator)?;
+    let graph = get_ninja_graph(
+        build_path,
+        ctx,
+        generator.synthesize_edges,
+        generator.load_edges_variables,
+    )?;
+    let defaults = graph.defaults.clone();
+    Ok((graph.into_targets(generator.new_target), defaults))
+}
+
+pub fn parse_build_ninja(
+    generator: &str,
+    build_path: &Path,
+    ctx: &Context,
+) -> Result<Vec<Box<dyn NinjaTarget>>, String> {
+    Ok(parse_build_ninja_with_defaults(generator, build_path, ctx)?.0)
 }
@@ -0,0 +1,232 @@
+// Copyright 2026 ninja-to-soong authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::time::UNIX_EPOCH;
+
+use super::*;
+
+// To be bumped whenever the layout of the cache changes.
+const CACHE_MAGIC: &[u8] = b"n2s-ninja-cache-3\n";
+
+pub fn hash_content(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+// Parsing depends on the executable, a cache written by another build of ninja-to-soong cannot be
+// trusted.
+fn get_exe_stamp() -> u64 {
+    std::env::current_exe()
+        .and_then(|exe| exe.metadata())
+        .and_then(|metadata| metadata.modified())
+        .map(|modified| match modified.duration_since(UNIX_EPOCH) {
+            Ok(duration) => duration.as_nanos() as u64,
+            Err(_) => 0,
+        })
+        .unwrap_or_default()
+}
+
+#[derive(Default)]
+struct CacheWriter(Vec<u8>);
+
+impl CacheWriter {
+    fn write_u64(&mut self, value: u64) {
+        self.0.extend(value.to_le_bytes());
+    }
+    fn write_usize(&mut self, value: usize) {
+        self.write_u64(value as u64);
+    }
+    fn write_str(&mut self, value: &str) {
+        self.write_usize(value.len());
+        self.0.extend(value.as_bytes());
+    }
+    fn write_paths(&mut self, paths: &[PathBuf]) {
+        self.write_usize(paths.len());
+        for path in paths {
+            self.write_str(&path_to_string(path));
+        }
+    }
+    fn write_bindings(&mut self, bindings: &NinjaBindings) {
+        self.write_usize(bindings.len());
+        for (key, value) in bindings {
+            self.write_str(key);
+            self.write_str(value);
+        }
+    }
+    fn write_rule_cmd(&mut self, rule_cmd: &Option<NinjaRuleCmd>) {
+        let Some(rule_cmd) = rule_cmd else {
+            self.0.push(0);
+            return;
+        };
+        self.0.push(1);
+        self.write_str(&rule_cmd.command);
+        match &rule_cmd.rsp_info {
+            Some((rspfile, rspfile_content)) => {
+                self.0.push(1);
+                self.write_str(rspfile);
+                self.write_str(rspfile_content);
+            }
+            None => self.0.push(0),
+        }
+    }
+}
+
+struct CacheReader<'a>(&'a [u8]);
+
+impl<'a> CacheReader<'a> {
+    fn read_bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.0.len() < len {
+            return None;
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(bytes)
+    }
+    fn read_bool(&mut self) -> Option<bool> {
+        Some(self.read_bytes(1)?[0] != 0)
+    }
+    fn read_u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.read_bytes(8)?.try_into().ok()?))
+    }
+    fn read_usize(&mut self) -> Option<usize> {
+        usize::try_from(self.read_u64()?).ok()
+    }
+    fn read_str(&mut self) -> Option<String> {
+        let len = self.read_usize()?;
+        String::from_utf8(self.read_bytes(len)?.to_vec()).ok()
+    }
+    fn read_paths(&mut self) -> Option<Vec<PathBuf>> {
+        let mut paths = Vec::new();
+        for _ in 0..self.read_usize()? {
+            paths.push(PathBuf::from(self.read_str()?));
+        }
+        Some(paths)
+    }
+    fn read_bindings(&mut self) -> Option<NinjaBindings> {
+        let mut bindings = NinjaBindings::new();
+        for _ in 0..self.read_usize()? {
+            bindings.insert(self.read_str()?, self.read_str()?);
+        }
+        Some(bindings)
+    }
+    fn read_rule_cmd(&mut self) -> Option<Option<NinjaRuleCmd>> {
+        if !self.read_bool()? {
+            return Some(None);
+        }
+        let command = self.read_str()?;
+        let rsp_info = if self.read_bool()? {
+            Some((self.read_str()?, self.read_str()?))
+        } else {
+            None
+        };
+        Some(Some(NinjaRuleCmd { command, rsp_info }))
+    }
+}
+
+pub fn write_cache(cache_path: &Path, graph: &NinjaGraph) -> Result<(), String> {
+    let mut writer = CacheWriter::default();
+    writer.0.extend(CACHE_MAGIC);
+    writer.write_u64(get_exe_stamp());
+    writer.write_usize(graph.files.len());
+    for (file_path, hash) in &graph.files {
+        writer.write_str(&path_to_string(file_path));
+        match hash {
+            Some(hash) => {
+                writer.0.push(1);
+                writer.write_u64(*hash);
+            }
+            None => writer.0.push(0),
+        }
+    }
+    writer.write_usize(graph.globals.len());
+    for (file_path, globals) in &graph.globals {
+        writer.write_str(&path_to_string(file_path));
+        writer.write_bindings(globals);
+    }
+    writer.write_paths(&graph.defaults);
+    writer.write_usize(graph.edges.len());
+    for edge in &graph.edges {
+        let common = &edge.common;
+        writer.write_str(&common.rule);
+        writer.write_paths(&common.outputs);
+        writer.write_paths(&common.implicit_outputs);
+        writer.write_paths(&common.inputs);
+        writer.write_paths(&common.implicit_deps);
+        writer.write_paths(&common.order_only_deps);
+        writer.write_bindings(&common.variables);
+        writer.write_rule_cmd(&edge.rule_cmd);
+        writer.write_usize(edge.globals);
+    }
+    if let Some(cache_dir) = cache_path.parent() {
+        create_dir(cache_dir)?;
+    }
+    if let Err(err) = std::fs::write(cache_path, writer.0) {
+        return error!("write({cache_path:#?}) failed: '{err}'");
+    }
+    Ok(())
+}
+
+fn read_graph(reader: &mut CacheReader) -> Option<NinjaGraph> {
+    if reader.read_bytes(CACHE_MAGIC.len())? != CACHE_MAGIC || reader.read_u64()? != get_exe_stamp()
+    {
+        return None;
+    }
+    let mut files = Vec::new();
+    for _ in 0..reader.read_usize()? {
+        let file_path = PathBuf::from(reader.read_str()?);
+        let hash = if reader.read_bool()? {
+            Some(reader.read_u64()?)
+        } else {
+            None
+        };
+        let current_hash = if file_path.exists() {
+            Some(hash_content(&read_file(&file_path).ok()?))
+        } else {
+            None
+        };
+        if current_hash != hash {
+            return None;
+        }
+        files.push((file_path, hash));
+    }
+    let mut globals = Vec::new();
+    for _ in 0..reader.read_usize()? {
+        globals.push((PathBuf::from(reader.read_str()?), reader.read_bindings()?));
+    }
+    let defaults = reader.read_paths()?;
+    let mut edges = Vec::new();
+    for _ in 0..reader.read_usize()? {
+        let common = NinjaTargetCommon {
+            rule: reader.read_str()?,
+            outputs: reader.read_paths()?,
+            implicit_outputs: reader.read_paths()?,
+            inputs: reader.read_paths()?,
+            implicit_deps: reader.read_paths()?,
+            order_only_deps: reader.read_paths()?,
+            variables: reader.read_bindings()?,
+        };
+        let rule_cmd = reader.read_rule_cmd()?;
+        let edge_globals = reader.read_usize()?;
+        if edge_globals >= globals.len() {
+            return None;
+        }
+        let mut edge = NinjaEdge::new(common, rule_cmd);
+        edge.globals = edge_globals;
+        edges.push(edge);
+    }
+    Some(NinjaGraph {
+        edges,
+        globals,
+        defaults,
+        files,
+    })
+}
+
+// Return the cached graph only if none of the Ninja files it comes from has changed since it was
+// written.
+pub fn read_cache(cache_path: &Path) -> Option<NinjaGraph> {
+    let content = std::fs::read(cache_path).ok()?;
+    read_graph(&mut CacheReader(&content))
+}
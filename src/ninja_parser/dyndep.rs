@@ -0,0 +1,139 @@
+// Copyright 2026 ninja-to-soong authors
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+// Implicit outputs and inputs discovered at build time for the output of an edge.
+struct NinjaDyndep {
+    implicit_outputs: Vec<PathBuf>,
+    implicit_inputs: Vec<PathBuf>,
+}
+
+fn parse_dyndep_build(
+    line: &str,
+    lines: &mut NinjaLines,
+    scope: &NinjaScope,
+) -> Result<(PathBuf, NinjaDyndep), NinjaSyntaxError> {
+    let build_line = parse_build_line(line)?;
+    if build_line.rule != "dyndep"
+        || build_line.outputs.len() != 1
+        || !build_line.inputs.is_empty()
+        || !build_line.order_only_deps.is_empty()
+    {
+        return error!("parse_dyndep_build failed: '{line}'").map_err(NinjaSyntaxError::from);
+    }
+    parse_bindings(lines, |key, _value| {
+        // 'restat' is the only binding allowed, it does not matter to generate Soong modules.
+        if key != "restat" {
+            return error!("parse_dyndep_build failed, unexpected variable '{key}'");
+        }
+        Ok(())
+    })?;
+    let evaluate_paths = |paths: &[&str]| -> Result<Vec<PathBuf>, String> {
+        let mut evaluated_paths = Vec::new();
+        for path in paths {
+            evaluated_paths.push(PathBuf::from(scope.evaluate(path)?));
+        }
+        Ok(evaluated_paths)
+    };
+    Ok((
+        PathBuf::from(scope.evaluate(build_line.outputs[0])?),
+        NinjaDyndep {
+            implicit_outputs: evaluate_paths(&build_line.implicit_outputs)?,
+            implicit_inputs: evaluate_paths(&build_line.implicit_deps)?,
+        },
+    ))
+}
+
+fn parse_dyndep_file(
+    file_path: &Path,
+    content: &str,
+) -> Result<HashMap<PathBuf, NinjaDyndep>, NinjaParseError> {
+    let scope = NinjaScope::default();
+    let mut dyndeps = HashMap::new();
+    let mut has_version = false;
+    let mut lines = NinjaLines::new(content);
+    while let Some(line) = lines.next() {
+        let line = line.as_ref();
+        if line.is_empty() || line.starts_with("#") || line.starts_with(" ") {
+            continue;
+        }
+        let position = lines.get_position();
+        let result = if !has_version {
+            match parse_key_value(line) {
+                Ok(("ninja_dyndep_version", _)) => {
+                    has_version = true;
+                    Ok(())
+                }
+                _ => error!("parse_dyndep_file failed, expected 'ninja_dyndep_version'")
+                    .map_err(NinjaSyntaxError::from),
+            }
+        } else {
+            parse_dyndep_build(line, &mut lines, &scope).map(|(output, dyndep)| {
+                dyndeps.insert(output, dyndep);
+            })
+        };
+        if let Err(err) = result {
+            let (line, column) = lines.get_line_column(position, err.offset);
+            return Err(NinjaParseError::Syntax {
+                location: NinjaLocation {
+                    file: PathBuf::from(file_path),
+                    line,
+                    column,
+                    snippet: String::from(lines.get_raw_lines(position)),
+                },
+                message: err.message,
+            });
+        }
+    }
+    Ok(dyndeps)
+}
+
+// Dyndep files are generated while building. The ones already present in the build directory are
+// loaded to add their implicit outputs and inputs to the edges referencing them.
+pub fn load_dyndep_files(build_path: &Path, state: &mut NinjaState) -> Result<(), NinjaParseError> {
+    let mut dyndep_files = HashMap::new();
+    for edge in &mut state.edges {
+        let Some(dyndep_file) = &edge.dyndep else {
+            continue;
+        };
+        if !dyndep_files.contains_key(dyndep_file) {
+            let file_path = build_path.join(dyndep_file);
+            let dyndeps = if file_path.exists() {
+                let content = match read_file(&file_path) {
+                    Ok(content) => content,
+                    Err(message) => {
+                        return Err(NinjaParseError::Read {
+                            file: file_path,
+                            message,
+                        })
+                    }
+                };
+                state
+                    .files
+                    .push((file_path.clone(), Some(hash_content(&content))));
+                Some(parse_dyndep_file(&file_path, &content)?)
+            } else {
+                state.files.push((file_path, None));
+                None
+            };
+            dyndep_files.insert(dyndep_file.clone(), dyndeps);
+        }
+        let Some(Some(dyndeps)) = dyndep_files.get(dyndep_file) else {
+            continue;
+        };
+        for output in &edge.common.outputs {
+            let Some(dyndep) = dyndeps.get(output) else {
+                continue;
+            };
+            edge.common
+                .implicit_outputs
+                .extend(dyndep.implicit_outputs.clone());
+            edge.common
+                .implicit_deps
+                .extend(dyndep.implicit_inputs.clone());
+            state.deps.extend(dyndep.implicit_inputs.iter().cloned());
+        }
+    }
+    Ok(())
+}
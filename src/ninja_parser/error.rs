@@ -0,0 +1,94 @@
+// Copyright 2026 ninja-to-soong authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::fmt;
+
+use crate::utils::*;
+
+#[derive(Debug, Clone)]
+pub struct NinjaLocation {
+    pub file: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    // Statement as written in the file, continuation lines included.
+    pub snippet: String,
+}
+
+impl fmt::Display for NinjaLocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{0}:{1}:{2}:\n{3}",
+            path_to_string(&self.file),
+            self.line,
+            self.column,
+            self.snippet
+        )
+    }
+}
+
+// Error in a statement, at a byte offset of its logical line. Errors not coming from the tokenizer
+// are reported at the beginning of the statement.
+#[derive(Debug)]
+pub struct NinjaSyntaxError {
+    pub offset: usize,
+    pub message: String,
+}
+
+impl NinjaSyntaxError {
+    pub fn at(offset: usize) -> impl FnOnce(String) -> Self {
+        move |message| Self { offset, message }
+    }
+}
+
+impl From<String> for NinjaSyntaxError {
+    fn from(message: String) -> Self {
+        Self { offset: 0, message }
+    }
+}
+
+#[derive(Debug)]
+pub enum NinjaParseError {
+    Read {
+        file: PathBuf,
+        message: String,
+    },
+    Syntax {
+        location: NinjaLocation,
+        message: String,
+    },
+    DuplicatePool {
+        location: NinjaLocation,
+        pool: String,
+    },
+    UnknownPool {
+        location: NinjaLocation,
+        pool: String,
+    },
+    Cache {
+        file: PathBuf,
+        message: String,
+    },
+}
+
+impl fmt::Display for NinjaParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Read { file, message } => write!(f, "Could not read {file:#?}: {message}"),
+            Self::Syntax { location, message } => write!(f, "{message}\n{location}"),
+            Self::DuplicatePool { location, pool } => {
+                write!(f, "duplicate pool '{pool}'\n{location}")
+            }
+            Self::UnknownPool { location, pool } => write!(f, "unknown pool '{pool}'\n{location}"),
+            Self::Cache { file, message } => {
+                write!(f, "Could not write Ninja cache {file:#?}: {message}")
+            }
+        }
+    }
+}
+
+impl From<NinjaParseError> for String {
+    fn from(err: NinjaParseError) -> Self {
+        err.to_string()
+    }
+}
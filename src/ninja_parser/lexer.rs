@@ -0,0 +1,271 @@
+// Copyright 2026 ninja-to-soong authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::borrow::Cow;
+
+use super::NinjaSyntaxError;
+use crate::utils::*;
+
+#[derive(Debug, PartialEq)]
+pub enum NinjaToken<'a> {
+    // Unevaluated path, escapes and variables are kept as written in the file.
+    Path(&'a str),
+    Colon,
+    Pipe,
+    PipeAt,
+    PipePipe,
+}
+
+struct NinjaLine<'a> {
+    number: usize,
+    begin: usize,
+    end: usize,
+    line: Cow<'a, str>,
+}
+
+// Position of a line in a Ninja file, used to report errors.
+#[derive(Clone, Copy)]
+pub struct NinjaLinePosition {
+    pub number: usize,
+    begin: usize,
+}
+
+// Logical lines of a Ninja file. Lines ending with '$' are joined with the next one, dropping the
+// newline and the indentation of the next line. Only continued lines are copied.
+pub struct NinjaLines<'a> {
+    content: &'a str,
+    offset: usize,
+    next_line_number: usize,
+    peeked: Option<NinjaLine<'a>>,
+    position: NinjaLinePosition,
+    end: usize,
+}
+
+fn is_continued(line: &str) -> bool {
+    let dollars = line.len() - line.trim_end_matches('$').len();
+    dollars % 2 == 1
+}
+
+impl<'a> NinjaLines<'a> {
+    pub fn new(content: &'a str) -> Self {
+        Self {
+            content,
+            offset: 0,
+            next_line_number: 1,
+            peeked: None,
+            position: NinjaLinePosition {
+                number: 0,
+                begin: 0,
+            },
+            end: 0,
+        }
+    }
+    // Position of the last line returned.
+    pub fn get_position(&self) -> NinjaLinePosition {
+        self.position
+    }
+    // Lines returned since 'position', as written in the file.
+    pub fn get_raw_lines(&self, position: NinjaLinePosition) -> &'a str {
+        &self.content[position.begin..self.end]
+    }
+    // Line and column in the file of a byte offset of the logical line at 'position'.
+    pub fn get_line_column(&self, position: NinjaLinePosition, offset: usize) -> (usize, usize) {
+        let mut number = position.number;
+        let mut offset = offset;
+        for (index, line) in self.get_raw_lines(position).split('\n').enumerate() {
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            let indent = match index {
+                0 => 0,
+                _ => line.len() - line.trim_start_matches(' ').len(),
+            };
+            if !is_continued(line) {
+                return (number, indent + offset + 1);
+            }
+            let len = line.len() - indent - 1;
+            if offset < len {
+                return (number, indent + offset + 1);
+            }
+            offset -= len;
+            number += 1;
+        }
+        (number, offset + 1)
+    }
+    pub fn next_if<F>(&mut self, predicate: F) -> Option<Cow<'a, str>>
+    where
+        F: FnOnce(&str) -> bool,
+    {
+        if self.peeked.is_none() {
+            self.peeked = self.read_line();
+        }
+        match &self.peeked {
+            Some(peeked) if predicate(&peeked.line) => self.next(),
+            _ => None,
+        }
+    }
+    fn next_physical_line(&mut self) -> (&'a str, usize) {
+        let rest = &self.content[self.offset..];
+        let (line, next_offset) = match rest.find('\n') {
+            Some(index) => (&rest[..index], self.offset + index + 1),
+            None => (rest, self.content.len()),
+        };
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        let end = self.offset + line.len();
+        self.offset = next_offset;
+        self.next_line_number += 1;
+        (line, end)
+    }
+    fn read_line(&mut self) -> Option<NinjaLine<'a>> {
+        if self.offset == self.content.len() {
+            return None;
+        }
+        let number = self.next_line_number;
+        let begin = self.offset;
+        let (line, mut end) = self.next_physical_line();
+        if !is_continued(line) || line.trim_start().starts_with('#') {
+            return Some(NinjaLine {
+                number,
+                begin,
+                end,
+                line: Cow::Borrowed(line),
+            });
+        }
+        let mut joined = String::from(&line[..line.len() - 1]);
+        while self.offset < self.content.len() {
+            let (line, line_end) = self.next_physical_line();
+            let line = line.trim_start_matches(' ');
+            end = line_end;
+            if !is_continued(line) {
+                joined += line;
+                break;
+            }
+            joined += &line[..line.len() - 1];
+        }
+        Some(NinjaLine {
+            number,
+            begin,
+            end,
+            line: Cow::Owned(joined),
+        })
+    }
+}
+
+impl<'a> Iterator for NinjaLines<'a> {
+    type Item = Cow<'a, str>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let line = match self.peeked.take() {
+            Some(line) => line,
+            None => self.read_line()?,
+        };
+        self.position = NinjaLinePosition {
+            number: line.number,
+            begin: line.begin,
+        };
+        self.end = line.end;
+        Some(line.line)
+    }
+}
+
+pub fn tokenize<'a>(line: &'a str) -> Result<Vec<NinjaToken<'a>>, NinjaSyntaxError> {
+    let mut tokens = Vec::new();
+    let mut begin = 0;
+    let mut chars = line.char_indices().peekable();
+    let push_path = |tokens: &mut Vec<NinjaToken<'a>>, begin: usize, end: usize| {
+        if begin < end {
+            tokens.push(NinjaToken::Path(&line[begin..end]));
+        }
+    };
+    while let Some((index, c)) = chars.next() {
+        match c {
+            ' ' => {
+                push_path(&mut tokens, begin, index);
+                begin = index + 1;
+            }
+            ':' => {
+                push_path(&mut tokens, begin, index);
+                tokens.push(NinjaToken::Colon);
+                begin = index + 1;
+            }
+            '|' => {
+                push_path(&mut tokens, begin, index);
+                begin = index + 1;
+                tokens.push(if chars.next_if(|(_, c)| *c == '|').is_some() {
+                    begin += 1;
+                    NinjaToken::PipePipe
+                } else if chars.next_if(|(_, c)| *c == '@').is_some() {
+                    begin += 1;
+                    NinjaToken::PipeAt
+                } else {
+                    NinjaToken::Pipe
+                });
+            }
+            '$' => match chars.next() {
+                Some((_, '{')) => loop {
+                    match chars.next() {
+                        Some((_, '}')) => break,
+                        Some(_) => (),
+                        None => {
+                            return error!("tokenize failed, missing '}}': '{line}'")
+                                .map_err(NinjaSyntaxError::at(index))
+                        }
+                    }
+                },
+                Some(_) => (),
+                None => {
+                    return error!("tokenize failed, unexpected end of line: '{line}'")
+                        .map_err(NinjaSyntaxError::at(index))
+                }
+            },
+            _ => (),
+        }
+    }
+    push_path(&mut tokens, begin, line.len());
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenize_escaped_paths() {
+        assert_eq!(
+            tokenize("a$ b.o c$:d.o: cc e$$f.c ${dir}/g.c | h || i |@ j").unwrap(),
+            vec![
+                NinjaToken::Path("a$ b.o"),
+                NinjaToken::Path("c$:d.o"),
+                NinjaToken::Colon,
+                NinjaToken::Path("cc"),
+                NinjaToken::Path("e$$f.c"),
+                NinjaToken::Path("${dir}/g.c"),
+                NinjaToken::Pipe,
+                NinjaToken::Path("h"),
+                NinjaToken::PipePipe,
+                NinjaToken::Path("i"),
+                NinjaToken::PipeAt,
+                NinjaToken::Path("j"),
+            ]
+        );
+        assert_eq!(tokenize("a.o: cc ${dir").unwrap_err().offset, 8);
+        assert_eq!(tokenize("a.o: cc b$").unwrap_err().offset, 9);
+    }
+
+    #[test]
+    fn continued_lines() {
+        let mut lines = NinjaLines::new("a $\n    b\nc $$\nd\n");
+        assert_eq!(lines.next().as_deref(), Some("a b"));
+        assert_eq!(lines.next().as_deref(), Some("c $$"));
+        assert_eq!(lines.next().as_deref(), Some("d"));
+        assert_eq!(lines.next().as_deref(), None);
+    }
+
+    #[test]
+    fn line_column_of_continued_lines() {
+        let mut lines = NinjaLines::new("x\nbuild a.o: $\n    cc b$\n  .c\n");
+        lines.next();
+        assert_eq!(lines.next().as_deref(), Some("build a.o: cc b.c"));
+        let position = lines.get_position();
+        assert_eq!(lines.get_line_column(position, 0), (2, 1));
+        assert_eq!(lines.get_line_column(position, 11), (3, 5));
+        assert_eq!(lines.get_line_column(position, 15), (4, 3));
+    }
+}
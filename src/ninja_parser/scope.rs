@@ -0,0 +1,359 @@
+// Copyright 2026 ninja-to-soong authors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::utils::*;
+
+pub type NinjaBindings = HashMap<String, String>;
+
+// A subninja file only sees its parent scope as it was when the subninja statement was read. Every
+// variable and rule is stored with the version of the scope it was added at, so that the parent
+// scope can be shared with subninja files parsed concurrently.
+#[derive(Default)]
+pub struct NinjaScope {
+    variables: HashMap<String, Vec<(usize, String)>>,
+    rules: HashMap<String, (usize, NinjaBindings)>,
+    version: usize,
+    parent: Option<(Arc<NinjaScope>, usize)>,
+}
+
+impl NinjaScope {
+    pub fn new(parent: Option<(Arc<NinjaScope>, usize)>) -> Self {
+        Self {
+            variables: HashMap::new(),
+            rules: HashMap::new(),
+            version: 0,
+            parent,
+        }
+    }
+    pub fn get_version(&self) -> usize {
+        self.version
+    }
+    fn get_variables_at(&self, version: usize) -> NinjaBindings {
+        let mut variables = match &self.parent {
+            Some((parent, parent_version)) => parent.get_variables_at(*parent_version),
+            None => NinjaBindings::new(),
+        };
+        for (name, values) in &self.variables {
+            if let Some((_, value)) = values.iter().rev().find(|(v, _)| *v <= version) {
+                variables.insert(name.clone(), value.clone());
+            }
+        }
+        variables
+    }
+    // Variables visible from this scope, including the ones inherited from its parents.
+    pub fn get_variables(&self) -> NinjaBindings {
+        self.get_variables_at(self.version)
+    }
+    fn lookup_at(&self, name: &str, version: usize) -> Option<&str> {
+        let value = self
+            .variables
+            .get(name)
+            .and_then(|values| values.iter().rev().find(|(v, _)| *v <= version));
+        match value {
+            Some((_, value)) => Some(value),
+            None => self
+                .parent
+                .as_ref()
+                .and_then(|(parent, version)| parent.lookup_at(name, *version)),
+        }
+    }
+    pub fn lookup(&self, name: &str) -> Option<&str> {
+        self.lookup_at(name, self.version)
+    }
+    fn get_rule_at(&self, name: &str, version: usize) -> Option<&NinjaBindings> {
+        match self.rules.get(name) {
+            Some((v, rule)) if *v <= version => Some(rule),
+            _ => self
+                .parent
+                .as_ref()
+                .and_then(|(parent, version)| parent.get_rule_at(name, *version)),
+        }
+    }
+    pub fn get_rule(&self, name: &str) -> Option<&NinjaBindings> {
+        self.get_rule_at(name, self.version)
+    }
+    pub fn add_rule(&mut self, name: String, bindings: NinjaBindings) {
+        self.version += 1;
+        self.rules.insert(name, (self.version, bindings));
+    }
+    pub fn add_variable(&mut self, name: String, value: &str) -> Result<(), String> {
+        let value = self.evaluate(value)?;
+        self.version += 1;
+        self.variables
+            .entry(name)
+            .or_default()
+            .push((self.version, value));
+        Ok(())
+    }
+    pub fn evaluate(&self, value: &str) -> Result<String, String> {
+        evaluate(value, &|name| {
+            Ok(String::from(self.lookup(name).unwrap_or_default()))
+        })
+    }
+}
+
+pub struct NinjaEdgeScope<'a> {
+    pub bindings: NinjaBindings,
+    rule: Option<&'a NinjaBindings>,
+    inputs: String,
+    inputs_newline: String,
+    outputs: String,
+    scope: &'a NinjaScope,
+}
+
+impl<'a> NinjaEdgeScope<'a> {
+    pub fn new(rule: &str, scope: &'a NinjaScope) -> Self {
+        Self {
+            bindings: HashMap::new(),
+            rule: scope.get_rule(rule),
+            inputs: String::new(),
+            inputs_newline: String::new(),
+            outputs: String::new(),
+            scope,
+        }
+    }
+    pub fn has_rule(&self) -> bool {
+        self.rule.is_some()
+    }
+    // Like ninja, edge bindings are evaluated in the enclosing scope only: they do not see the
+    // other bindings of the same edge.
+    pub fn add_binding(&mut self, name: String, value: &str) -> Result<(), String> {
+        let value = self.scope.evaluate(value)?;
+        self.bindings.insert(name, value);
+        Ok(())
+    }
+    pub fn set_paths(&mut self, inputs: &[PathBuf], outputs: &[PathBuf]) {
+        let join = |paths: &[PathBuf], separator: &str| {
+            paths
+                .iter()
+                .map(path_to_string)
+                .collect::<Vec<_>>()
+                .join(separator)
+        };
+        self.inputs = join(inputs, " ");
+        self.inputs_newline = join(inputs, "\n");
+        self.outputs = join(outputs, " ");
+    }
+    fn lookup(&self, name: &str) -> Option<&str> {
+        match self.bindings.get(name) {
+            Some(value) => Some(value),
+            None => self.scope.lookup(name),
+        }
+    }
+    // Paths see the edge bindings and the enclosing scopes.
+    pub fn evaluate(&self, value: &str) -> Result<String, String> {
+        evaluate(value, &|name| {
+            Ok(String::from(self.lookup(name).unwrap_or_default()))
+        })
+    }
+    // Edge bindings take precedence over rule bindings. Rule bindings also see '$in', '$out' and
+    // the other rule bindings.
+    pub fn get_binding(&self, name: &str) -> Result<Option<String>, String> {
+        if let Some(value) = self.bindings.get(name) {
+            return Ok(Some(value.clone()));
+        }
+        let Some(rule) = self.rule else {
+            return Ok(None);
+        };
+        let Some(value) = rule.get(name) else {
+            return Ok(None);
+        };
+        Ok(Some(self.evaluate_in_rule(value, &mut vec![name])?))
+    }
+    fn evaluate_in_rule<'b>(
+        &'b self,
+        value: &str,
+        stack: &mut Vec<&'b str>,
+    ) -> Result<String, String> {
+        let lookup = |name: &str| -> Result<String, String> {
+            Ok(match name {
+                "in" => self.inputs.clone(),
+                "in_newline" => self.inputs_newline.clone(),
+                "out" => self.outputs.clone(),
+                _ => {
+                    if let Some(value) = self.bindings.get(name) {
+                        value.clone()
+                    } else if let Some((name, value)) =
+                        self.rule.and_then(|rule| rule.get_key_value(name))
+                    {
+                        if stack.contains(&name.as_str()) {
+                            return error!("cycle in rule variables: '{name}'");
+                        }
+                        let mut stack = stack.clone();
+                        stack.push(name);
+                        self.evaluate_in_rule(value, &mut stack)?
+                    } else {
+                        String::from(self.scope.lookup(name).unwrap_or_default())
+                    }
+                }
+            })
+        };
+        evaluate(value, &lookup)
+    }
+}
+
+fn is_simple_var_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+fn evaluate<F>(value: &str, lookup: &F) -> Result<String, String>
+where
+    F: Fn(&str) -> Result<String, String>,
+{
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.char_indices().peekable();
+    while let Some((_, c)) = chars.next() {
+        if c != '$' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some((_, '$')) => result.push('$'),
+            Some((_, ' ')) => result.push(' '),
+            Some((_, ':')) => result.push(':'),
+            Some((_, '\n')) => while chars.next_if(|(_, c)| *c == ' ').is_some() {},
+            Some((begin, '{')) => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '}')) => break,
+                        Some((_, c)) if is_simple_var_char(c) || c == '.' => name.push(c),
+                        _ => return error!("bad $-escape in '{value}' at {begin}"),
+                    }
+                }
+                result += &lookup(&name)?;
+            }
+            Some((_, c)) if is_simple_var_char(c) => {
+                let mut name = String::from(c);
+                while let Some((_, c)) = chars.next_if(|(_, c)| is_simple_var_char(*c)) {
+                    name.push(c);
+                }
+                result += &lookup(&name)?;
+            }
+            _ => return error!("bad $-escape in '{value}'"),
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(bindings: &[(&str, &str)]) -> NinjaBindings {
+        bindings
+            .iter()
+            .map(|(name, value)| (String::from(*name), String::from(*value)))
+            .collect()
+    }
+
+    #[test]
+    fn evaluate_escapes() {
+        let mut scope = NinjaScope::new(None);
+        scope.add_variable(String::from("var"), "value").unwrap();
+        assert_eq!(
+            scope
+                .evaluate("$$ $  $: $var ${var}.o $\n    next")
+                .unwrap(),
+            "$   : value value.o next"
+        );
+        assert!(scope.evaluate("${var").is_err());
+        assert!(scope.evaluate("$!").is_err());
+    }
+
+    #[test]
+    fn variables_are_evaluated_when_added() {
+        let mut scope = NinjaScope::new(None);
+        scope.add_variable(String::from("a"), "1").unwrap();
+        scope.add_variable(String::from("b"), "$a").unwrap();
+        scope.add_variable(String::from("a"), "2").unwrap();
+        assert_eq!(scope.lookup("a"), Some("2"));
+        assert_eq!(scope.lookup("b"), Some("1"));
+    }
+
+    #[test]
+    fn subninja_sees_parent_at_its_statement() {
+        let mut parent = NinjaScope::new(None);
+        parent.add_variable(String::from("a"), "before").unwrap();
+        parent.add_rule(String::from("cc"), rule(&[("command", "cc")]));
+        let version = parent.get_version();
+        parent.add_variable(String::from("a"), "after").unwrap();
+        parent.add_variable(String::from("b"), "after").unwrap();
+        parent.add_rule(String::from("cxx"), rule(&[("command", "cxx")]));
+        let parent = Arc::new(parent);
+
+        let mut child = NinjaScope::new(Some((parent.clone(), version)));
+        assert_eq!(child.lookup("a"), Some("before"));
+        assert_eq!(child.lookup("b"), None);
+        assert!(child.get_rule("cc").is_some());
+        assert!(child.get_rule("cxx").is_none());
+        child.add_variable(String::from("a"), "child").unwrap();
+        assert_eq!(child.lookup("a"), Some("child"));
+        assert_eq!(parent.lookup("a"), Some("after"));
+        assert_eq!(child.get_variables().get("a"), Some(&String::from("child")));
+    }
+
+    #[test]
+    fn edge_bindings_and_rule_bindings() {
+        let mut scope = NinjaScope::new(None);
+        scope.add_variable(String::from("flags"), "-O2").unwrap();
+        scope.add_rule(
+            String::from("cc"),
+            rule(&[
+                ("command", "cc $flags $extra -c $in -o $out"),
+                ("extra", "-g"),
+                ("rspfile_content", "$in_newline"),
+            ]),
+        );
+        let mut edge_scope = NinjaEdgeScope::new("cc", &scope);
+        edge_scope.set_paths(
+            &[PathBuf::from("a b.c"), PathBuf::from("c.c")],
+            &[PathBuf::from("a.o")],
+        );
+        edge_scope
+            .add_binding(String::from("flags"), "$flags -Wall")
+            .unwrap();
+        assert_eq!(
+            edge_scope.get_binding("command").unwrap(),
+            Some(String::from("cc -O2 -Wall -g -c a b.c c.c -o a.o"))
+        );
+        assert_eq!(
+            edge_scope.get_binding("rspfile_content").unwrap(),
+            Some(String::from("a b.c\nc.c"))
+        );
+        assert_eq!(edge_scope.get_binding("pool").unwrap(), None);
+    }
+
+    #[test]
+    fn edge_bindings_see_enclosing_scope_only() {
+        let mut scope = NinjaScope::new(None);
+        scope.add_variable(String::from("a"), "file").unwrap();
+        scope.add_rule(String::from("r"), rule(&[("command", "r $b")]));
+        let mut edge_scope = NinjaEdgeScope::new("r", &scope);
+        edge_scope.add_binding(String::from("a"), "x").unwrap();
+        edge_scope.add_binding(String::from("b"), "$a").unwrap();
+        assert_eq!(
+            edge_scope.get_binding("b").unwrap(),
+            Some(String::from("file"))
+        );
+        assert_eq!(edge_scope.evaluate("$a.o").unwrap(), "x.o");
+        assert_eq!(
+            edge_scope.get_binding("command").unwrap(),
+            Some(String::from("r file"))
+        );
+    }
+
+    #[test]
+    fn rule_bindings_cycle() {
+        let mut scope = NinjaScope::new(None);
+        scope.add_rule(
+            String::from("cc"),
+            rule(&[("command", "$a"), ("a", "$b"), ("b", "$a")]),
+        );
+        let edge_scope = NinjaEdgeScope::new("cc", &scope);
+        assert!(edge_scope.get_binding("command").is_err());
+    }
+}
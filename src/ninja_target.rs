@@ -7,10 +7,12 @@ use crate::utils::*;
 
 pub mod cmake;
 pub mod common;
+pub mod compiledb;
 pub mod gn;
 pub mod meson;
 
 pub use cmake::*;
+pub use compiledb::*;
 pub use gn::*;
 pub use meson::*;
 
@@ -19,16 +21,48 @@ pub struct NinjaRuleCmd {
     pub command: String,
     pub rsp_info: Option<(String, String)>,
 }
-pub type NinjaRulesMap = HashMap<String, NinjaRuleCmd>;
 
 pub enum NinjaRule {
     Binary,
+    Test,
     StaticLibrary,
     SharedLibrary,
+    ObjectLibrary,
+    Rust(RustCrateType),
+    JavaLibrary,
+    AidlInterface,
     CustomCommand(NinjaRuleCmd),
     None,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SourceLanguage {
+    C,
+    Cpp,
+    Asm,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RustCrateType {
+    Binary,
+    Library,
+    ProcMacro,
+    StaticLibrary,
+    SharedLibrary,
+}
+
+// Crate built by a rustc edge, as described by its command line.
+#[derive(Debug, Clone)]
+pub struct RustCrate {
+    pub crate_type: RustCrateType,
+    pub crate_name: String,
+    pub edition: Option<String>,
+    pub cfgs: Vec<String>,
+    pub features: Vec<String>,
+    // Crates passed with '--extern', by name.
+    pub externs: Vec<(String, PathBuf)>,
+}
+
 #[derive(Debug)]
 pub struct NinjaTargetCommon {
     pub rule: String,
@@ -40,9 +74,9 @@ pub struct NinjaTargetCommon {
     pub variables: HashMap<String, String>,
 }
 
+// Classify the rule and extract the flags of a target, according to the Ninja generator that wrote it.
 pub trait NinjaTarget: std::fmt::Debug {
     // MANDATORY FUNCTIONS
-    fn new(common: NinjaTargetCommon) -> Self;
     fn get_common(&self) -> &NinjaTargetCommon;
     fn get_rule(&self) -> Result<NinjaRule, String>;
     fn get_sources(&self, build_path: &Path) -> Result<Vec<PathBuf>, String>;
@@ -55,7 +89,41 @@ pub trait NinjaTarget: std::fmt::Debug {
     fn get_cflags(&self) -> Vec<String>;
     // OPTIONAL FUNCTIONS
     fn set_globals(&mut self, _globals: HashMap<String, String>) {}
-    fn set_rule(&mut self, _rules: &NinjaRulesMap) {}
+    fn set_ninja_file(&mut self, _file_path: &Path) {}
+    fn set_rule(&mut self, _rule_cmd: NinjaRuleCmd) {}
+    fn get_language(&self) -> Option<SourceLanguage> {
+        common::get_language(&self.get_common().inputs)
+    }
+    fn get_rust_crate(&self) -> Result<Option<RustCrate>, String> {
+        Ok(None)
+    }
+    // Toolchain building the target, for generators building for several of them.
+    fn get_toolchain(&self) -> Option<String> {
+        None
+    }
+    // Whether the static libraries of the target have to be linked as a group.
+    fn has_link_group(&self) -> bool {
+        false
+    }
+    // Libraries of the target in their link order, the ones of each kind follow each other unless the
+    // generator knows it.
+    fn get_libs_ordered(&self) -> Vec<(PathBuf, common::LibraryKind)> {
+        [
+            (
+                self.get_libs_static_whole(),
+                common::LibraryKind::StaticWhole,
+            ),
+            (self.get_libs_static(), common::LibraryKind::Static),
+            (self.get_libs_shared(), common::LibraryKind::Shared),
+        ]
+        .into_iter()
+        .flat_map(|(libs, kind)| libs.into_iter().map(move |lib| (lib, kind)))
+        .collect()
+    }
+    // Other targets built from the same sources, generated along with the target.
+    fn get_variants(&self) -> Vec<PathBuf> {
+        Vec::new()
+    }
     // COMMON FUNCTIONS
     fn get_name(&self) -> PathBuf {
         PathBuf::from(&self.get_common().outputs[0])
@@ -75,6 +143,137 @@ pub trait NinjaTarget: std::fmt::Debug {
     fn get_implicit_ouputs(&self) -> &Vec<PathBuf> {
         &self.get_common().implicit_outputs
     }
+    fn is_phony(&self) -> bool {
+        self.get_common().rule == "phony"
+    }
+}
+
+// Targets of generators only known at runtime are boxed. Every method is forwarded, default ones
+// included, for the boxed target to behave like the target itself.
+impl<T: NinjaTarget + ?Sized> NinjaTarget for Box<T> {
+    fn get_common(&self) -> &NinjaTargetCommon {
+        self.as_ref().get_common()
+    }
+    fn get_rule(&self) -> Result<NinjaRule, String> {
+        self.as_ref().get_rule()
+    }
+    fn get_sources(&self, build_path: &Path) -> Result<Vec<PathBuf>, String> {
+        self.as_ref().get_sources(build_path)
+    }
+    fn get_libs_static(&self) -> Vec<PathBuf> {
+        self.as_ref().get_libs_static()
+    }
+    fn get_libs_static_whole(&self) -> Vec<PathBuf> {
+        self.as_ref().get_libs_static_whole()
+    }
+    fn get_libs_shared(&self) -> Vec<PathBuf> {
+        self.as_ref().get_libs_shared()
+    }
+    fn get_link_flags(&self) -> (Option<PathBuf>, Vec<String>) {
+        self.as_ref().get_link_flags()
+    }
+    fn get_defines(&self) -> Vec<String> {
+        self.as_ref().get_defines()
+    }
+    fn get_includes(&self, build_path: &Path) -> Vec<PathBuf> {
+        self.as_ref().get_includes(build_path)
+    }
+    fn get_cflags(&self) -> Vec<String> {
+        self.as_ref().get_cflags()
+    }
+    fn set_globals(&mut self, globals: HashMap<String, String>) {
+        self.as_mut().set_globals(globals)
+    }
+    fn set_ninja_file(&mut self, file_path: &Path) {
+        self.as_mut().set_ninja_file(file_path)
+    }
+    fn set_rule(&mut self, rule_cmd: NinjaRuleCmd) {
+        self.as_mut().set_rule(rule_cmd)
+    }
+    fn get_language(&self) -> Option<SourceLanguage> {
+        self.as_ref().get_language()
+    }
+    fn get_rust_crate(&self) -> Result<Option<RustCrate>, String> {
+        self.as_ref().get_rust_crate()
+    }
+    fn get_toolchain(&self) -> Option<String> {
+        self.as_ref().get_toolchain()
+    }
+    fn has_link_group(&self) -> bool {
+        self.as_ref().has_link_group()
+    }
+    fn get_libs_ordered(&self) -> Vec<(PathBuf, common::LibraryKind)> {
+        self.as_ref().get_libs_ordered()
+    }
+    fn get_variants(&self) -> Vec<PathBuf> {
+        self.as_ref().get_variants()
+    }
+    fn get_name(&self) -> PathBuf {
+        self.as_ref().get_name()
+    }
+    fn get_outputs(&self) -> &Vec<PathBuf> {
+        self.as_ref().get_outputs()
+    }
+    fn get_implicit_ouputs(&self) -> &Vec<PathBuf> {
+        self.as_ref().get_implicit_ouputs()
+    }
+    fn is_phony(&self) -> bool {
+        self.as_ref().is_phony()
+    }
+}
+
+// A Ninja generator is plugged in by implementing this trait on its 'NinjaTarget', and by adding it
+// to 'NINJA_GENERATORS' to be selectable by name.
+pub trait NinjaGenerator: NinjaTarget + Sized + 'static {
+    const NAME: &'static str;
+    // Generators not writing Ninja files synthesize the edges of their targets from the build
+    // directory instead.
+    const SYNTHESIZE_EDGES: Option<NinjaEdgesSynthesizer> = None;
+    // Generators describing their targets outside of the Ninja files add variables to the edges
+    // of these targets, by output.
+    const LOAD_EDGES_VARIABLES: Option<NinjaEdgesVariablesLoader> = None;
+    fn new(common: NinjaTargetCommon) -> Self;
+}
+
+pub type NinjaEdgesSynthesizer = fn(&Path) -> Result<Vec<NinjaTargetCommon>, String>;
+pub type NinjaEdgesVariablesLoader =
+    fn(&Path) -> Result<HashMap<PathBuf, HashMap<String, String>>, String>;
+
+pub struct NinjaGeneratorEntry {
+    pub name: &'static str,
+    pub new_target: fn(NinjaTargetCommon) -> Box<dyn NinjaTarget>,
+    pub synthesize_edges: Option<NinjaEdgesSynthesizer>,
+    pub load_edges_variables: Option<NinjaEdgesVariablesLoader>,
+}
+
+fn new_boxed_target<T: NinjaGenerator>(common: NinjaTargetCommon) -> Box<dyn NinjaTarget> {
+    Box::new(T::new(common))
+}
+
+const fn ninja_generator<T: NinjaGenerator>() -> NinjaGeneratorEntry {
+    NinjaGeneratorEntry {
+        name: T::NAME,
+        new_target: new_boxed_target::<T>,
+        synthesize_edges: T::SYNTHESIZE_EDGES,
+        load_edges_variables: T::LOAD_EDGES_VARIABLES,
+    }
+}
+
+pub const NINJA_GENERATORS: &[NinjaGeneratorEntry] = &[
+    ninja_generator::<CmakeNinjaTarget>(),
+    ninja_generator::<CompiledbNinjaTarget>(),
+    ninja_generator::<GnNinjaTarget>(),
+    ninja_generator::<MesonNinjaTarget>(),
+];
+
+pub fn get_ninja_generator(name: &str) -> Result<&'static NinjaGeneratorEntry, String> {
+    match NINJA_GENERATORS
+        .iter()
+        .find(|generator| generator.name == name)
+    {
+        Some(generator) => Ok(generator),
+        None => error!("Unknown Ninja generator '{name}'"),
+    }
 }
 
 pub struct NinjaTargetToGenMapEntry {
@@ -155,6 +354,53 @@ impl NinjaTargetsToGenMap {
         self.insert(&target);
         self
     }
+    // Phony targets are replaced by the targets they resolve to. The name and stem of a phony
+    // target resolving to several targets cannot be shared, only its module type is.
+    pub fn resolve_phony<T>(mut self, targets_map: &NinjaTargetsMap<T>) -> Result<Self, String>
+    where
+        T: NinjaTarget,
+    {
+        for target in self.get_targets() {
+            let resolved = targets_map.resolve_phony(&target)?;
+            if resolved.is_empty() || resolved.contains(&target) {
+                continue;
+            }
+            let entry = self.0.remove(&target).unwrap();
+            if resolved.len() == 1 {
+                self.0.entry(resolved[0].clone()).or_insert(entry);
+                continue;
+            }
+            for resolved_target in resolved {
+                self.0
+                    .entry(resolved_target)
+                    .or_insert(NinjaTargetToGenMapEntry {
+                        name: None,
+                        stem: None,
+                        module_type: entry.module_type.clone(),
+                    });
+            }
+        }
+        Ok(self)
+    }
+    // Variants of a target are generated with it, their module type is the one of their rule.
+    pub fn add_variants<T>(mut self, targets_map: &NinjaTargetsMap<T>) -> Self
+    where
+        T: NinjaTarget,
+    {
+        for target in self.get_targets() {
+            let Some(target) = targets_map.get(&target) else {
+                continue;
+            };
+            for variant in target.get_variants() {
+                self.0.entry(variant).or_insert(NinjaTargetToGenMapEntry {
+                    name: None,
+                    stem: None,
+                    module_type: None,
+                });
+            }
+        }
+        self
+    }
 }
 
 #[derive(Debug)]
@@ -181,6 +427,15 @@ where
     pub fn get(&self, key: &Path) -> Option<&&T> {
         self.0.get(key)
     }
+    pub fn resolve_phony(&self, target: &Path) -> Result<Vec<PathBuf>, String> {
+        let mut resolved = Vec::new();
+        self.traverse_from(vec![PathBuf::from(target)], false, |target| {
+            resolved.push(target.get_name());
+            Ok(false)
+        })?;
+        resolved.sort_unstable();
+        Ok(resolved)
+    }
     pub fn traverse_from<F>(
         &self,
         mut targets: Vec<PathBuf>,
@@ -201,7 +456,7 @@ where
             };
             targets_seen.extend(target.get_outputs().clone());
             targets_seen.extend(target.get_implicit_ouputs().clone());
-            if filter_target(target)? {
+            if target.is_phony() || filter_target(target)? {
                 targets.extend(target.get_inputs().clone());
                 if !only_inputs {
                     targets.extend(target.get_implicit_deps().clone());
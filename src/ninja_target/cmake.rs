@@ -6,10 +6,27 @@ use super::*;
 #[derive(Debug)]
 pub struct CmakeNinjaTarget(NinjaTargetCommon);
 
-impl NinjaTarget for CmakeNinjaTarget {
+impl CmakeNinjaTarget {
+    // Object libraries are phony targets gathering the objects of their sources.
+    fn is_object_library(&self) -> bool {
+        self.0.rule == "phony"
+            && !self.0.inputs.is_empty()
+            && self
+                .0
+                .inputs
+                .iter()
+                .all(|input| ["o", "obj"].contains(&file_ext(input).as_str()))
+    }
+}
+
+impl NinjaGenerator for CmakeNinjaTarget {
+    const NAME: &'static str = "cmake";
     fn new(common: NinjaTargetCommon) -> Self {
         Self(common)
     }
+}
+
+impl NinjaTarget for CmakeNinjaTarget {
     fn get_common(&self) -> &NinjaTargetCommon {
         &self.0
     }
@@ -64,12 +81,16 @@ impl NinjaTarget for CmakeNinjaTarget {
                     NinjaRule::None
                 } else {
                     NinjaRule::CustomCommand(NinjaRuleCmd {
-                        command: common::get_cmd(command),
+                        command: String::from(command),
                         rsp_info: None,
                     })
                 }
-            } else if self.0.rule.starts_with("CXX_EXECUTABLE") {
+            } else if self.0.rule.starts_with("CXX_EXECUTABLE")
+                || self.0.rule.starts_with("C_EXECUTABLE")
+            {
                 NinjaRule::Binary
+            } else if self.is_object_library() {
+                NinjaRule::ObjectLibrary
             } else {
                 NinjaRule::None
             },
@@ -99,6 +120,18 @@ impl NinjaTarget for CmakeNinjaTarget {
         };
         common::get_libs_shared(libs)
     }
+    fn get_libs_ordered(&self) -> Vec<(PathBuf, common::LibraryKind)> {
+        let Some(libs) = self.0.variables.get("LINK_LIBRARIES") else {
+            return Vec::new();
+        };
+        common::get_link_libraries(libs)
+    }
+    fn has_link_group(&self) -> bool {
+        self.0
+            .variables
+            .get("LINK_LIBRARIES")
+            .is_some_and(|libs| common::has_link_group(libs))
+    }
     fn get_link_flags(&self) -> (Option<PathBuf>, Vec<String>) {
         let Some(flags) = self.0.variables.get("LINK_FLAGS") else {
             return (None, Vec::new());
@@ -123,4 +156,19 @@ impl NinjaTarget for CmakeNinjaTarget {
         };
         common::get_cflags(flags)
     }
+    fn is_phony(&self) -> bool {
+        self.0.rule == "phony" && !self.is_object_library()
+    }
+    fn get_language(&self) -> Option<SourceLanguage> {
+        // 'ASM', 'ASM-ATT', 'ASM_NASM', ... are all assembly dialects.
+        if self.0.rule.starts_with("ASM") {
+            Some(SourceLanguage::Asm)
+        } else if self.0.rule.starts_with("C_COMPILER") {
+            Some(SourceLanguage::C)
+        } else if self.0.rule.starts_with("CXX_COMPILER") {
+            Some(SourceLanguage::Cpp)
+        } else {
+            common::get_language(&self.0.inputs)
+        }
+    }
 }
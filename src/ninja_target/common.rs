@@ -1,6 +1,8 @@
 // Copyright 2024 ninja-to-soong authors
 // SPDX-License-Identifier: Apache-2.0
 
+use super::{RustCrate, RustCrateType, SourceLanguage};
+use crate::compile_commands::{split_command, FLAGS_WITH_ARGUMENT};
 use crate::utils::*;
 
 #[derive(PartialEq, Clone, Copy, Debug)]
@@ -11,7 +13,8 @@ pub enum LibraryKind {
     Unspecified,
 }
 
-fn get_libs(libs: &str, target: LibraryKind) -> Vec<PathBuf> {
+// Libraries of a link command line, in their link order.
+pub fn get_link_libraries(libs: &str) -> Vec<(PathBuf, LibraryKind)> {
     let mut prev_state: Option<LibraryKind> = None;
     let mut state: Option<LibraryKind> = None;
     libs.split(" ")
@@ -29,11 +32,10 @@ fn get_libs(libs: &str, target: LibraryKind) -> Vec<PathBuf> {
                 {
                     return None;
                 }
-                if state == Some(target.clone())
-                    || (target == LibraryKind::Shared && state.is_none())
-                {
-                    return Some(PathBuf::from(format!("lib{library}")));
-                }
+                return Some((
+                    PathBuf::from(format!("lib{library}")),
+                    state.unwrap_or(LibraryKind::Shared),
+                ));
             } else if let Some(arg) = lib.strip_prefix("-Wl,") {
                 if arg == "-Bstatic" {
                     state = Some(LibraryKind::Static)
@@ -46,22 +48,76 @@ fn get_libs(libs: &str, target: LibraryKind) -> Vec<PathBuf> {
                     state = prev_state.clone()
                 }
             } else if lib_ext.contains(".a") || lib_ext.starts_with("a") {
-                if (target == LibraryKind::Static && state != Some(LibraryKind::StaticWhole))
-                    || (target == LibraryKind::StaticWhole
-                        && state == Some(LibraryKind::StaticWhole))
-                {
-                    return Some(PathBuf::from(lib));
-                }
+                return Some((
+                    PathBuf::from(lib),
+                    match state {
+                        Some(LibraryKind::StaticWhole) => LibraryKind::StaticWhole,
+                        _ => LibraryKind::Static,
+                    },
+                ));
             } else if lib_ext.contains(".so") || lib_ext.starts_with("so") {
-                if target == LibraryKind::Shared {
-                    return Some(PathBuf::from(lib));
-                }
+                return Some((PathBuf::from(lib), LibraryKind::Shared));
             }
             return None;
         })
         .collect()
 }
 
+fn get_libs(libs: &str, target: LibraryKind) -> Vec<PathBuf> {
+    get_link_libraries(libs)
+        .into_iter()
+        .filter_map(|(lib, kind)| if kind == target { Some(lib) } else { None })
+        .collect()
+}
+
+// Static libraries depending on each other are either grouped, or repeated after each other in the
+// link command line ('liba.a libb.a liba.a libb.a'). A library only repeated after another one
+// ('liba.a libb.a liba.a') just has to be linked after it.
+pub fn has_link_group(libs: &str) -> bool {
+    let mut in_group = false;
+    for arg in libs.split(" ") {
+        if arg == "-Wl,--start-group" {
+            in_group = true;
+        } else if arg == "-Wl,--end-group" {
+            in_group = false;
+        } else if in_group && !get_libs_static(arg).is_empty() {
+            return true;
+        }
+    }
+    let static_libs = get_libs(libs, LibraryKind::Static);
+    let get_bounds = |lib: &PathBuf| {
+        (
+            static_libs.iter().position(|static_lib| static_lib == lib),
+            static_libs.iter().rposition(|static_lib| static_lib == lib),
+        )
+    };
+    static_libs.iter().any(|lib| {
+        let (first, last) = get_bounds(lib);
+        static_libs.iter().any(|other_lib| {
+            let (other_first, other_last) = get_bounds(other_lib);
+            first < other_first && other_first < last && last < other_last
+        })
+    })
+}
+
+pub const LIBRARY_KIND_ARGS: &[&str] = &[
+    "-Wl,-Bstatic",
+    "-Wl,-Bdynamic",
+    "-Wl,--whole-archive",
+    "-Wl,--no-whole-archive",
+];
+
+pub const LINK_GROUP_ARGS: &[&str] = &["-Wl,--start-group", "-Wl,--end-group"];
+
+pub fn is_library(arg: &str) -> bool {
+    if arg.starts_with("-") {
+        return false;
+    }
+    let lib_name = file_name(Path::new(arg));
+    let lib_ext = lib_name.split_once(".").unwrap_or_default().1;
+    lib_ext == "a" || lib_ext == "so" || lib_ext.starts_with("so.") || lib_ext.ends_with(".so")
+}
+
 pub fn get_libs_static(libs: &str) -> Vec<PathBuf> {
     get_libs(libs, LibraryKind::Static)
 }
@@ -120,15 +176,20 @@ pub fn get_link_flags(flags: &str) -> (Option<PathBuf>, Vec<String>) {
 }
 
 pub fn get_cflags(flags: &str) -> Vec<String> {
-    flags
-        .split(" ")
-        .filter_map(|flag| {
-            if flag.is_empty() {
-                return None;
-            }
-            Some(String::from(flag))
-        })
-        .collect()
+    let mut cflags = Vec::new();
+    let mut flags = flags.split(" ").filter(|flag| !flag.is_empty());
+    while let Some(flag) = flags.next() {
+        // Flags are kept with their value, not to be split from it when sorted.
+        match FLAGS_WITH_ARGUMENT
+            .contains(&flag)
+            .then(|| flags.next())
+            .flatten()
+        {
+            Some(value) => cflags.push(format!("{flag} {value}")),
+            None => cflags.push(String::from(flag)),
+        }
+    }
+    cflags
 }
 
 pub fn get_sources(inputs: &Vec<PathBuf>, build_path: &Path) -> Vec<PathBuf> {
@@ -138,6 +199,87 @@ pub fn get_sources(inputs: &Vec<PathBuf>, build_path: &Path) -> Vec<PathBuf> {
         .collect()
 }
 
-pub fn get_cmd(cmd: &str) -> String {
-    cmd.replace("$ ", " ")
+// Language of an object, according to the extension of its source.
+pub fn get_language(inputs: &[PathBuf]) -> Option<SourceLanguage> {
+    match file_ext(inputs.first()?).as_str() {
+        "c" => Some(SourceLanguage::C),
+        "cc" | "cpp" | "cxx" | "c++" | "C" => Some(SourceLanguage::Cpp),
+        "S" | "s" | "asm" => Some(SourceLanguage::Asm),
+        _ => None,
+    }
+}
+
+fn get_rust_crate_type(crate_type: &str) -> Result<RustCrateType, String> {
+    Ok(match crate_type {
+        "bin" => RustCrateType::Binary,
+        "lib" | "rlib" | "dylib" => RustCrateType::Library,
+        "proc-macro" => RustCrateType::ProcMacro,
+        "staticlib" => RustCrateType::StaticLibrary,
+        "cdylib" => RustCrateType::SharedLibrary,
+        _ => return error!("Unknown crate type '{crate_type}'"),
+    })
+}
+
+// rustc arguments are quoted for the shell ('--cfg 'feature="std"'') or escaped for GN
+// ('--cfg=feature=\"std\"').
+fn unquote_rust_arg(arg: &str) -> String {
+    arg.trim_matches('\'').replace("\\\"", "\"")
+}
+
+pub fn get_rust_crate(command: &str) -> Result<RustCrate, String> {
+    let mut crate_type = None;
+    let mut crate_name = None;
+    let mut edition = None;
+    let mut cfgs = Vec::new();
+    let mut features = Vec::new();
+    let mut externs = Vec::new();
+    let mut args = split_command(command).into_iter();
+    while let Some(arg) = args.next() {
+        let (option, value) = match arg.split_once("=") {
+            Some((option, value)) if option.starts_with("--") => {
+                (String::from(option), Some(String::from(value)))
+            }
+            _ => (arg, None),
+        };
+        let get_value = || {
+            value
+                .or_else(|| args.next())
+                .map(|value| unquote_rust_arg(&value))
+        };
+        match option.as_str() {
+            "--crate-type" => crate_type = get_value(),
+            "--crate-name" => crate_name = get_value(),
+            "--edition" => edition = get_value(),
+            "--cfg" => {
+                let Some(cfg) = get_value() else {
+                    continue;
+                };
+                match cfg
+                    .strip_prefix("feature=\"")
+                    .and_then(|feature| feature.strip_suffix("\""))
+                {
+                    Some(feature) => features.push(String::from(feature)),
+                    None => cfgs.push(cfg),
+                }
+            }
+            "--extern" => {
+                // Crates of the sysroot ('--extern proc_macro') come without a path.
+                if let Some((name, path)) = get_value().as_deref().and_then(|e| e.split_once("=")) {
+                    externs.push((String::from(name), PathBuf::from(path)));
+                }
+            }
+            _ => (),
+        }
+    }
+    let Some(crate_name) = crate_name else {
+        return error!("No crate name in '{command}'");
+    };
+    Ok(RustCrate {
+        crate_type: get_rust_crate_type(crate_type.as_deref().unwrap_or("bin"))?,
+        crate_name,
+        edition,
+        cfgs,
+        features,
+        externs,
+    })
 }
@@ -0,0 +1,262 @@
+// Copyright 2026 ninja-to-soong authors
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use crate::compile_commands::*;
+
+// Output of the link commands of the build, as printed by 'make V=1' or libtool.
+const LINK_LOG_FILE: &str = "link.log";
+
+const COMPILE_RULE: &str = "COMPILE";
+const ARCHIVE_RULE: &str = "ARCHIVE";
+const LINK_SHARED_RULE: &str = "LINK_SHARED";
+const LINK_EXECUTABLE_RULE: &str = "LINK_EXECUTABLE";
+
+// Flags of the compilation database are already split, they are kept one per line so that they do
+// not have to be parsed again.
+const FLAGS_SEPARATOR: &str = "\n";
+
+// Targets of projects that do not generate Ninja files (autotools, plain Makefiles), synthesized from
+// their compilation database and the log of their link commands.
+#[derive(Debug)]
+pub struct CompiledbNinjaTarget(NinjaTargetCommon);
+
+fn new_edge(rule: &str, output: PathBuf, inputs: Vec<PathBuf>) -> NinjaTargetCommon {
+    NinjaTargetCommon {
+        rule: String::from(rule),
+        outputs: vec![output],
+        implicit_outputs: Vec::new(),
+        inputs,
+        implicit_deps: Vec::new(),
+        order_only_deps: Vec::new(),
+        variables: HashMap::new(),
+    }
+}
+
+// Paths inside the build directory are relative to it, like the ones written by Ninja generators.
+fn get_edge_path<P: AsRef<Path>>(path: P, directory: &Path, build_path: &Path) -> PathBuf {
+    strip_prefix(canonicalize_path(path, directory), build_path)
+}
+
+fn get_compile_edges(
+    compile_commands: &CompileCommands,
+    build_path: &Path,
+) -> Result<Vec<NinjaTargetCommon>, String> {
+    let mut edges = Vec::new();
+    for command in compile_commands.get_commands() {
+        let Some(output) = &command.output else {
+            return error!("No output for {:#?} in compile_commands.json", command.file);
+        };
+        let mut edge = new_edge(
+            COMPILE_RULE,
+            strip_prefix(output, build_path),
+            vec![command.file.clone()],
+        );
+        let includes = command
+            .flags
+            .includes
+            .iter()
+            .map(path_to_string)
+            .collect::<Vec<_>>();
+        for (key, values) in [
+            ("DEFINES", &command.flags.defines),
+            ("INCLUDES", &includes),
+            ("FLAGS", &command.flags.cflags),
+        ] {
+            edge.variables
+                .insert(String::from(key), values.join(FLAGS_SEPARATOR));
+        }
+        edges.push(edge);
+    }
+    Ok(edges)
+}
+
+fn parse_link_command(
+    args: Vec<String>,
+    directory: &Path,
+    build_path: &Path,
+) -> Option<NinjaTargetCommon> {
+    let mut args = args.into_iter();
+    let tool = file_name(Path::new(&args.next()?));
+    if tool == "ar" || tool.ends_with("-ar") {
+        // Skip the operations ('rcs', 'cru', ...).
+        args.next()?;
+        let output = get_edge_path(args.next()?, directory, build_path);
+        let inputs = args
+            .map(|arg| get_edge_path(arg, directory, build_path))
+            .collect::<Vec<_>>();
+        if inputs.is_empty() {
+            return None;
+        }
+        return Some(new_edge(ARCHIVE_RULE, output, inputs));
+    }
+    let mut output = None;
+    let mut shared = false;
+    let mut inputs = Vec::new();
+    let mut libs = Vec::new();
+    let mut lib_deps = Vec::new();
+    let mut link_flags = Vec::new();
+    while let Some(arg) = args.next() {
+        if arg == "-o" {
+            output = args.next();
+        } else if arg == "-c" {
+            // Compile commands come from the compilation database.
+            return None;
+        } else if arg == "-shared" {
+            shared = true;
+        } else if arg.starts_with("-L") {
+            continue;
+        } else if arg.ends_with(".o") {
+            inputs.push(get_edge_path(&arg, directory, build_path));
+        } else if common::is_library(&arg) {
+            let lib = get_edge_path(&arg, directory, build_path);
+            libs.push(path_to_string(&lib));
+            lib_deps.push(lib);
+        } else if arg.starts_with("-l")
+            || common::LIBRARY_KIND_ARGS.contains(&arg.as_str())
+            || common::LINK_GROUP_ARGS.contains(&arg.as_str())
+        {
+            libs.push(arg);
+        } else {
+            link_flags.push(arg);
+        }
+    }
+    let output = output?;
+    if inputs.is_empty() {
+        return None;
+    }
+    let mut edge = new_edge(
+        if shared {
+            LINK_SHARED_RULE
+        } else {
+            LINK_EXECUTABLE_RULE
+        },
+        get_edge_path(output, directory, build_path),
+        inputs,
+    );
+    edge.implicit_deps = lib_deps;
+    edge.variables
+        .insert(String::from("LINK_LIBRARIES"), libs.join(" "));
+    edge.variables
+        .insert(String::from("LINK_FLAGS"), link_flags.join(" "));
+    Some(edge)
+}
+
+fn parse_link_log(content: &str, build_path: &Path) -> Vec<NinjaTargetCommon> {
+    let mut edges = Vec::new();
+    // Recursive make runs commands from the directories it enters.
+    let mut directories = vec![PathBuf::from(build_path)];
+    for line in content.lines() {
+        let directory = directories.last().unwrap();
+        if let Some((_, entered)) = line.split_once("Entering directory ") {
+            let entered = canonicalize_path(entered.trim_matches(['\'', '`', '"']), directory);
+            directories.push(entered);
+            continue;
+        } else if line.contains("Leaving directory ") {
+            if directories.len() > 1 {
+                directories.pop();
+            }
+            continue;
+        }
+        let line = line.strip_prefix("libtool: link: ").unwrap_or(line);
+        edges.extend(parse_link_command(
+            split_command(line),
+            directory,
+            build_path,
+        ));
+    }
+    edges
+}
+
+impl CompiledbNinjaTarget {
+    fn synthesize_edges(build_path: &Path) -> Result<Vec<NinjaTargetCommon>, String> {
+        let compile_commands = CompileCommands::new(build_path)?;
+        let mut edges = get_compile_edges(&compile_commands, build_path)?;
+        let link_log_path = build_path.join(LINK_LOG_FILE);
+        if !link_log_path.exists() {
+            return error!("{link_log_path:#?} not found");
+        }
+        edges.extend(parse_link_log(&read_file(&link_log_path)?, build_path));
+        Ok(edges)
+    }
+    fn get_flags(&self, key: &str) -> Vec<String> {
+        let Some(flags) = self.0.variables.get(key) else {
+            return Vec::new();
+        };
+        flags
+            .split(FLAGS_SEPARATOR)
+            .filter(|flag| !flag.is_empty())
+            .map(String::from)
+            .collect()
+    }
+    fn get_link_libraries(&self) -> &str {
+        match self.0.variables.get("LINK_LIBRARIES") {
+            Some(libs) => libs,
+            None => "",
+        }
+    }
+}
+
+impl NinjaGenerator for CompiledbNinjaTarget {
+    const NAME: &'static str = "compiledb";
+    const SYNTHESIZE_EDGES: Option<NinjaEdgesSynthesizer> = Some(Self::synthesize_edges);
+    fn new(common: NinjaTargetCommon) -> Self {
+        Self(common)
+    }
+}
+
+impl NinjaTarget for CompiledbNinjaTarget {
+    fn get_common(&self) -> &NinjaTargetCommon {
+        &self.0
+    }
+
+    fn get_rule(&self) -> Result<NinjaRule, String> {
+        Ok(match self.0.rule.as_str() {
+            ARCHIVE_RULE => NinjaRule::StaticLibrary,
+            LINK_SHARED_RULE => NinjaRule::SharedLibrary,
+            LINK_EXECUTABLE_RULE => NinjaRule::Binary,
+            _ => NinjaRule::None,
+        })
+    }
+    fn get_sources(&self, build_path: &Path) -> Result<Vec<PathBuf>, String> {
+        Ok(common::get_sources(&self.0.inputs, build_path))
+    }
+    fn get_libs_static(&self) -> Vec<PathBuf> {
+        common::get_libs_static(self.get_link_libraries())
+    }
+    fn get_libs_static_whole(&self) -> Vec<PathBuf> {
+        common::get_libs_static_whole(self.get_link_libraries())
+    }
+    fn get_libs_shared(&self) -> Vec<PathBuf> {
+        common::get_libs_shared(self.get_link_libraries())
+    }
+    fn get_libs_ordered(&self) -> Vec<(PathBuf, common::LibraryKind)> {
+        common::get_link_libraries(self.get_link_libraries())
+    }
+    fn has_link_group(&self) -> bool {
+        common::has_link_group(self.get_link_libraries())
+    }
+    fn get_link_flags(&self) -> (Option<PathBuf>, Vec<String>) {
+        let Some(flags) = self
+            .0
+            .variables
+            .get("LINK_FLAGS")
+            .filter(|flags| !flags.is_empty())
+        else {
+            return (None, Vec::new());
+        };
+        common::get_link_flags(flags)
+    }
+    fn get_defines(&self) -> Vec<String> {
+        self.get_flags("DEFINES")
+    }
+    fn get_includes(&self, build_path: &Path) -> Vec<PathBuf> {
+        self.get_flags("INCLUDES")
+            .iter()
+            .map(|include| canonicalize_path(include, build_path))
+            .collect()
+    }
+    fn get_cflags(&self) -> Vec<String> {
+        self.get_flags("FLAGS")
+    }
+}
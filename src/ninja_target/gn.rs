@@ -2,117 +2,371 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use super::*;
+use crate::compile_commands::split_command;
+use common::LibraryKind;
 
 const SHARED_LIB: &str = "solink";
+const LOADABLE_MODULE: &str = "solink_module";
 const STATIC_LIB: &str = "alink";
+const EXECUTABLE: &str = "link";
+const LINK_RULES: &[&str] = &[SHARED_LIB, LOADABLE_MODULE, EXECUTABLE];
+// Executables linking googletest are tests.
+const TEST_LIBRARIES: &[&str] = &["gtest", "gtest_main", "gmock", "gmock_main"];
+// 'rust_bin', 'rust_rlib', 'rust_macro', 'rust_staticlib', ...
+const RUST_RULE_PREFIX: &str = "rust_";
+// Variables holding the flags of the objects built by each compile rule.
+const COMPILE_RULES_CFLAGS: &[(&str, &[&str])] = &[
+    ("cc", &["cflags", "cflags_c"]),
+    ("cxx", &["cflags", "cflags_cc"]),
+    ("objc", &["cflags", "cflags_objc"]),
+    ("objcxx", &["cflags", "cflags_objcc"]),
+    ("asm", &["asmflags"]),
+];
+// Android steps are GN actions, recognized by the tool they run.
+const JAVA_TOOLS: &[&str] = &["javac", "kotlinc", "compile_java.py", "compile_kt.py"];
+const DEX_TOOLS: &[&str] = &["d8", "dex.py"];
+const AIDL_TOOLS: &[&str] = &["aidl", "aidl.py"];
+const PYTHON_INTERPRETERS: &[&str] = &["python", "python3"];
 
 #[derive(Debug)]
 pub struct GnNinjaTarget {
     rule_cmd: Option<NinjaRuleCmd>,
     common: NinjaTargetCommon,
     globals: Option<HashMap<String, String>>,
+    toolchain: Option<String>,
 }
 
-impl NinjaTarget for GnNinjaTarget {
+impl GnNinjaTarget {
+    // Variables of the edge take precedence over the ones of the scope of its file.
+    fn get_variable(&self, key: &str) -> Option<&String> {
+        self.common
+            .variables
+            .get(key)
+            .or_else(|| self.globals.as_ref().and_then(|globals| globals.get(key)))
+    }
+    // Rules of the tools of the toolchains other than the default one are prefixed by the name of
+    // their toolchain ('clang_x64_cxx').
+    fn get_tool_rule(&self) -> &str {
+        match &self.toolchain {
+            Some(toolchain) => self
+                .common
+                .rule
+                .strip_prefix(toolchain.as_str())
+                .and_then(|rule| rule.strip_prefix("_"))
+                .unwrap_or(&self.common.rule),
+            None => &self.common.rule,
+        }
+    }
+    // Source sets are stamp (or phony with recent GN) edges gathering the objects of their sources.
+    fn is_source_set(&self) -> bool {
+        ["stamp", "phony"].contains(&self.get_tool_rule())
+            && !self.common.inputs.is_empty()
+            && self
+                .common
+                .inputs
+                .iter()
+                .all(|input| ["o", "obj"].contains(&file_ext(input).as_str()))
+    }
+    // Googletest is linked as a library ('libgtest.a', '-lgtest_main'), or as the objects of its
+    // source sets, in a directory named after them ('obj/third_party/googletest/gtest/*.o').
+    fn is_test(&self) -> bool {
+        if self.get_link_args().is_none() {
+            return false;
+        }
+        let libs = self.get_libs_ordered().into_iter().map(|(lib, _)| {
+            let lib_name = file_name(&lib);
+            let lib_name = lib_name.strip_prefix("lib").unwrap_or(&lib_name);
+            String::from(lib_name.split_once(".").map_or(lib_name, |(name, _)| name))
+        });
+        let source_sets = self
+            .common
+            .inputs
+            .iter()
+            .filter(|input| ["o", "obj"].contains(&file_ext(input).as_str()))
+            .filter_map(|object| object.parent().map(file_name));
+        libs.chain(source_sets)
+            .any(|name| TEST_LIBRARIES.contains(&name.as_str()))
+    }
+    fn get_cflags_keys(&self) -> Option<&'static [&'static str]> {
+        COMPILE_RULES_CFLAGS
+            .iter()
+            .find(|(rule, _)| *rule == self.get_tool_rule())
+            .map(|(_, keys)| *keys)
+    }
+    // Link line of the edge, without the wrapper script running the linker, with the content of
+    // its rspfile and without its output.
+    fn get_link_args(&self) -> Option<String> {
+        if !LINK_RULES.contains(&self.get_tool_rule()) {
+            return None;
+        }
+        let rule_cmd = self.rule_cmd.as_ref()?;
+        let mut args = split_command(&rule_cmd.command);
+        if let Some(separator) = args.iter().position(|arg| arg == "--") {
+            args.drain(..=separator);
+        }
+        let mut link_args = Vec::new();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg == "-o" {
+                args.next();
+                continue;
+            }
+            let arg = arg.replace("\"", "");
+            match (arg.strip_prefix("@"), &rule_cmd.rsp_info) {
+                (Some(rspfile), Some((rsp, rspfile_content))) if rspfile == rsp => {
+                    link_args.push(rspfile_content.clone())
+                }
+                _ => link_args.push(arg),
+            }
+        }
+        Some(link_args.join(" "))
+    }
+    fn get_args(&self) -> Vec<String> {
+        match &self.rule_cmd {
+            Some(rule_cmd) => split_command(&rule_cmd.command),
+            None => Vec::new(),
+        }
+    }
+    fn get_libs_of_kind(&self, kind: LibraryKind) -> Vec<PathBuf> {
+        self.get_libs_ordered()
+            .into_iter()
+            .filter_map(|(lib, lib_kind)| (lib_kind == kind).then_some(lib))
+            .collect()
+    }
+    // Tool run by an action, the script given to the interpreter for python ones.
+    fn get_action_tool(&self) -> Option<String> {
+        if !self.common.rule.ends_with("__rule") {
+            return None;
+        }
+        self.get_args()
+            .iter()
+            .map(|arg| file_name(Path::new(arg)))
+            .find(|arg| !PYTHON_INTERPRETERS.contains(&arg.as_str()))
+    }
+    fn runs_tool(&self, tools: &[&str]) -> bool {
+        self.get_action_tool()
+            .is_some_and(|tool| tools.contains(&tool.as_str()))
+    }
+}
+
+impl NinjaGenerator for GnNinjaTarget {
+    const NAME: &'static str = "gn";
     fn new(common: NinjaTargetCommon) -> Self {
         Self {
             rule_cmd: None,
             common,
             globals: None,
+            toolchain: None,
         }
     }
+}
+
+impl NinjaTarget for GnNinjaTarget {
     fn get_common(&self) -> &NinjaTargetCommon {
         &self.common
     }
 
     fn get_rule(&self) -> Result<NinjaRule, String> {
-        Ok(if self.common.rule == SHARED_LIB {
+        let tool_rule = self.get_tool_rule();
+        Ok(if tool_rule == SHARED_LIB || tool_rule == LOADABLE_MODULE {
             NinjaRule::SharedLibrary
-        } else if self.common.rule == STATIC_LIB {
+        } else if tool_rule == STATIC_LIB {
             NinjaRule::StaticLibrary
+        } else if tool_rule == EXECUTABLE && self.is_test() {
+            NinjaRule::Test
+        } else if tool_rule == EXECUTABLE {
+            NinjaRule::Binary
+        } else if self.is_source_set() {
+            NinjaRule::ObjectLibrary
+        } else if let Some(rust_crate) = self.get_rust_crate()? {
+            NinjaRule::Rust(rust_crate.crate_type)
+        } else if let Some(tool) = self.get_action_tool() {
+            if JAVA_TOOLS.contains(&tool.as_str()) {
+                NinjaRule::JavaLibrary
+            } else if AIDL_TOOLS.contains(&tool.as_str()) {
+                NinjaRule::AidlInterface
+            } else if DEX_TOOLS.contains(&tool.as_str()) {
+                // Soong dexes Java libraries itself.
+                NinjaRule::None
+            } else {
+                let Some(command) = self.rule_cmd.clone() else {
+                    return error!("No command in: {self:#?}");
+                };
+                NinjaRule::CustomCommand(command)
+            }
         } else if self.common.rule.ends_with("__rule") {
-            let Some(mut command) = self.rule_cmd.clone() else {
-                return error!("No command in: {self:#?}");
-            };
-            command.command = common::get_cmd(&command.command);
-            NinjaRule::CustomCommand(command)
+            return error!("No command in: {self:#?}");
         } else {
             NinjaRule::None
         })
     }
     fn get_sources(&self, build_path: &Path) -> Result<Vec<PathBuf>, String> {
-        if !(self.common.rule == "cxx" || self.common.rule == "cc" || self.common.rule == "asm") {
+        if !(self.get_tool_rule() == "cxx"
+            || self.get_tool_rule() == "cc"
+            || self.get_tool_rule() == "asm")
+        {
             return Ok(Vec::new());
         }
         Ok(common::get_sources(&self.common.inputs, build_path))
     }
     fn get_libs_static_whole(&self) -> Vec<PathBuf> {
-        Vec::new()
+        self.get_libs_of_kind(LibraryKind::StaticWhole)
     }
     fn get_libs_static(&self) -> Vec<PathBuf> {
-        let mut static_libs = Vec::new();
-        for lib_key in ["libs", "solibs"] {
-            if let Some(libs) = self.common.variables.get(lib_key) {
-                static_libs.extend(common::get_libs_static(libs));
-            }
-        }
-        if self.common.rule == STATIC_LIB {
-            static_libs.push(self.common.outputs[0].clone());
-        }
-        static_libs
+        self.get_libs_of_kind(LibraryKind::Static)
     }
     fn get_libs_shared(&self) -> Vec<PathBuf> {
-        let mut shared_libs = Vec::new();
-        for lib_key in ["libs", "solibs"] {
-            if let Some(libs) = self.common.variables.get(lib_key) {
-                shared_libs.extend(common::get_libs_shared(libs));
-            }
-        }
-        if self.common.rule == SHARED_LIB {
-            shared_libs.push(self.common.outputs[0].clone());
-        }
-        shared_libs
+        self.get_libs_of_kind(LibraryKind::Shared)
     }
     fn get_link_flags(&self) -> (Option<PathBuf>, Vec<String>) {
-        let Some(libs) = self.common.variables.get("ldflags") else {
+        let Some(flags) = self.get_variable("ldflags") else {
             return (None, Vec::new());
         };
-        common::get_link_flags(libs)
+        // Libraries in 'ldflags' are already part of the libraries of the edge.
+        let flags = flags
+            .split(" ")
+            .filter(|flag| {
+                !flag.is_empty()
+                    && !common::is_library(flag)
+                    && !common::LIBRARY_KIND_ARGS.contains(flag)
+                    && !common::LINK_GROUP_ARGS.contains(flag)
+            })
+            .collect::<Vec<_>>();
+        if flags.is_empty() {
+            return (None, Vec::new());
+        }
+        common::get_link_flags(&flags.join(" "))
     }
     fn get_defines(&self) -> Vec<String> {
-        if let Some(globals) = &self.globals {
-            if let Some(defs) = globals.get("defines") {
-                return common::get_defines(defs);
-            }
+        if self.get_cflags_keys().is_none() {
+            return Vec::new();
+        }
+        match self.get_variable("defines") {
+            Some(defines) => common::get_defines(defines),
+            None => Vec::new(),
         }
-        Vec::new()
     }
     fn get_includes(&self, build_path: &Path) -> Vec<PathBuf> {
-        if let Some(globals) = &self.globals {
-            if let Some(incs) = globals.get("include_dirs") {
-                return common::get_includes(incs, build_path);
+        if self.runs_tool(AIDL_TOOLS) {
+            // Import directories are passed as '-I<dir>' to aidl, as a GN list to 'aidl.py'.
+            let mut includes = Vec::new();
+            let mut args = self.get_args().into_iter();
+            while let Some(arg) = args.next() {
+                if arg == "-I" {
+                    includes.extend(args.next());
+                } else if let Some(include) = arg.strip_prefix("-I") {
+                    includes.push(String::from(include));
+                } else if let Some(list) = arg.strip_prefix("--includes=") {
+                    includes.extend(
+                        list.trim_matches(['[', ']', '\''])
+                            .split(",")
+                            .map(|include| String::from(include.trim().trim_matches(['"', '\\'])))
+                            .filter(|include| !include.is_empty()),
+                    );
+                }
             }
+            return includes
+                .iter()
+                .map(|include| canonicalize_path(include, build_path))
+                .collect();
+        }
+        if self.get_cflags_keys().is_none() {
+            return Vec::new();
+        }
+        match self.get_variable("include_dirs") {
+            Some(includes) => common::get_includes(includes, build_path),
+            None => Vec::new(),
         }
-        Vec::new()
     }
     fn get_cflags(&self) -> Vec<String> {
         let mut cflags = Vec::new();
-        for cflag in ["cflags", "cflags_cc"] {
-            if let Some(globals) = &self.globals {
-                if let Some(defs) = globals.get(cflag) {
-                    cflags.append(&mut common::get_cflags(defs));
-                }
+        for key in self.get_cflags_keys().unwrap_or_default() {
+            if let Some(flags) = self.get_variable(key) {
+                cflags.append(&mut common::get_cflags(flags));
             }
         }
         cflags
     }
 
+    fn is_phony(&self) -> bool {
+        self.common.rule == "phony" && !self.is_source_set()
+    }
+    fn get_language(&self) -> Option<SourceLanguage> {
+        match self.get_tool_rule() {
+            "cc" => Some(SourceLanguage::C),
+            "cxx" => Some(SourceLanguage::Cpp),
+            "asm" => Some(SourceLanguage::Asm),
+            _ => None,
+        }
+    }
+
+    fn get_rust_crate(&self) -> Result<Option<RustCrate>, String> {
+        if !self.get_tool_rule().starts_with(RUST_RULE_PREFIX) {
+            return Ok(None);
+        }
+        let Some(crate_name) = self.get_variable("crate_name") else {
+            return Ok(None);
+        };
+        let mut command = format!("--crate-name {crate_name}");
+        if let Some(crate_type) = self.get_variable("crate_type") {
+            command += &format!(" --crate-type {crate_type}");
+        }
+        for key in ["rustflags", "externs"] {
+            if let Some(value) = self.get_variable(key) {
+                command += &format!(" {value}");
+            }
+        }
+        Ok(Some(common::get_rust_crate(&command)?))
+    }
+
+    fn get_toolchain(&self) -> Option<String> {
+        self.toolchain.clone()
+    }
+
+    // Libraries of the link line, followed by the ones of the 'libs' and 'solibs' variables it does
+    // not pass (rspfile only listing the inputs of the edge).
+    fn get_libs_ordered(&self) -> Vec<(PathBuf, LibraryKind)> {
+        let mut libs = match self.get_link_args() {
+            Some(link_args) => common::get_link_libraries(&link_args),
+            None => Vec::new(),
+        };
+        for lib_key in ["libs", "solibs"] {
+            if let Some(variable_libs) = self.get_variable(lib_key) {
+                for (lib, kind) in common::get_link_libraries(variable_libs) {
+                    if !libs.iter().any(|(link_lib, _)| *link_lib == lib) {
+                        libs.push((lib, kind));
+                    }
+                }
+            }
+        }
+        match self.get_tool_rule() {
+            STATIC_LIB => libs.push((self.common.outputs[0].clone(), LibraryKind::Static)),
+            SHARED_LIB => libs.push((self.common.outputs[0].clone(), LibraryKind::Shared)),
+            _ => (),
+        }
+        libs
+    }
+    fn has_link_group(&self) -> bool {
+        self.get_link_args()
+            .is_some_and(|link_args| common::has_link_group(&link_args))
+    }
+
     fn set_globals(&mut self, globals: HashMap<String, String>) {
         self.globals = Some(globals);
     }
-    fn set_rule(&mut self, rules: &NinjaRulesMap) {
-        if let Some(rule) = rules.get(&self.common.rule) {
-            self.rule_cmd = Some(rule.clone());
+    // Files of the default toolchain are at the root of the build directory ('toolchain.ninja',
+    // 'obj/...'), the ones of the other toolchains in a directory named after them
+    // ('clang_x64/toolchain.ninja', 'clang_x64/obj/...').
+    fn set_ninja_file(&mut self, file_path: &Path) {
+        let mut components = file_path.iter();
+        let (Some(first), Some(_)) = (components.next(), components.next()) else {
+            return;
+        };
+        if first != "obj" {
+            self.toolchain = Some(String::from(first.to_str().unwrap_or_default()));
         }
     }
+    fn set_rule(&mut self, rule_cmd: NinjaRuleCmd) {
+        self.rule_cmd = Some(rule_cmd);
+    }
 }
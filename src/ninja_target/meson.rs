@@ -2,32 +2,138 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use super::*;
+use crate::compile_commands::*;
+
+const INTRO_TARGETS_FILE: &str = "meson-info/intro-targets.json";
+// Edge variable holding the type of the target from the introspection data ('shared library',
+// 'shared module', 'executable', ...).
+const TARGET_TYPE: &str = "MESON_TARGET_TYPE";
+// Edge variable holding the files of the other library of 'both_libraries'. Meson describes them
+// as a shared and a static library sharing their name.
+const TARGET_VARIANTS: &str = "MESON_TARGET_VARIANTS";
+const LIBRARY_TYPES: &[&str] = &["shared library", "static library"];
 
 #[derive(Debug)]
 pub struct MesonNinjaTarget(NinjaTargetCommon);
 
+fn get_json_str(members: &[(String, JsonValue)], key: &str) -> Option<String> {
+    members.iter().find_map(|(member, value)| match value {
+        JsonValue::String(value) if member == key => Some(value.clone()),
+        _ => None,
+    })
+}
+
 impl MesonNinjaTarget {
+    // Introspection data is optional, targets are classified from their link command without it.
+    fn load_edges_variables(
+        build_path: &Path,
+    ) -> Result<HashMap<PathBuf, HashMap<String, String>>, String> {
+        let mut edges_variables = HashMap::new();
+        let file_path = build_path.join(INTRO_TARGETS_FILE);
+        if !file_path.exists() {
+            return Ok(edges_variables);
+        }
+        let targets = match parse_json(&read_file(&file_path)?) {
+            Ok(JsonValue::Array(targets)) => targets,
+            Ok(_) => return error!("{file_path:#?} is not an array"),
+            Err(err) => return error!("Could not parse {file_path:#?}: {err}"),
+        };
+        let mut libraries: HashMap<String, Vec<(String, Vec<PathBuf>)>> = HashMap::new();
+        let mut intro_targets = Vec::new();
+        for target in targets {
+            let JsonValue::Object(members) = target else {
+                return error!("{file_path:#?} targets are not objects");
+            };
+            let Some(target_type) = get_json_str(&members, "type") else {
+                continue;
+            };
+            let filenames = members.iter().find_map(|(member, value)| match value {
+                JsonValue::Array(filenames) if member == "filename" => Some(filenames),
+                _ => None,
+            });
+            let filenames = filenames
+                .into_iter()
+                .flatten()
+                .filter_map(|filename| match filename {
+                    JsonValue::String(filename) => Some(strip_prefix(
+                        canonicalize_path(filename, build_path),
+                        build_path,
+                    )),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            // Target ids end with the type of the target ('@sha', '@sta', ...).
+            let key = match get_json_str(&members, "id") {
+                Some(id) => String::from(id.rsplit_once("@").map_or(id.as_str(), |(key, _)| key)),
+                None => get_json_str(&members, "name").unwrap_or_default(),
+            };
+            if LIBRARY_TYPES.contains(&target_type.as_str()) {
+                libraries
+                    .entry(key.clone())
+                    .or_default()
+                    .push((target_type.clone(), filenames.clone()));
+            }
+            intro_targets.push((key, target_type, filenames));
+        }
+        for (key, target_type, filenames) in intro_targets {
+            let mut variables = HashMap::from([(String::from(TARGET_TYPE), target_type.clone())]);
+            let variants = libraries
+                .get(&key)
+                .filter(|_| LIBRARY_TYPES.contains(&target_type.as_str()))
+                .into_iter()
+                .flatten()
+                .filter(|(library_type, _)| *library_type != target_type)
+                .flat_map(|(_, filenames)| filenames.iter().map(path_to_string))
+                .collect::<Vec<_>>();
+            if !variants.is_empty() {
+                variables.insert(String::from(TARGET_VARIANTS), variants.join("\n"));
+            }
+            for filename in filenames {
+                edges_variables.insert(filename, variables.clone());
+            }
+        }
+        Ok(edges_variables)
+    }
+    fn is_shared_library(&self) -> bool {
+        let link_args = match self.0.variables.get("LINK_ARGS") {
+            Some(args) => args.split(" ").collect(),
+            None => Vec::new(),
+        };
+        let output_name = file_name(&self.0.outputs[0]);
+        link_args
+            .iter()
+            .any(|arg| *arg == "-shared" || arg.starts_with("-Wl,-soname"))
+            || output_name.ends_with(".so")
+            || output_name.contains(".so.")
+    }
+
     fn get_command(&self, command: &str) -> String {
         let Some(split) = command.split_once(" -- ") else {
-            return common::get_cmd(command);
+            return String::from(command);
         };
         let Some(capture) = split.0.split_once("--capture ") else {
-            return common::get_cmd(split.1);
+            return String::from(split.1);
         };
-        return common::get_cmd(split.1)
+        String::from(split.1)
             + " > "
             + if let Some(output) = capture.1.split_once(" ") {
                 output.0
             } else {
                 capture.1
-            };
+            }
     }
 }
 
-impl NinjaTarget for MesonNinjaTarget {
+impl NinjaGenerator for MesonNinjaTarget {
+    const NAME: &'static str = "meson";
+    const LOAD_EDGES_VARIABLES: Option<NinjaEdgesVariablesLoader> =
+        Some(Self::load_edges_variables);
     fn new(common: NinjaTargetCommon) -> Self {
         Self(common)
     }
+}
+
+impl NinjaTarget for MesonNinjaTarget {
     fn get_common(&self) -> &NinjaTargetCommon {
         &self.0
     }
@@ -35,12 +141,14 @@ impl NinjaTarget for MesonNinjaTarget {
     fn get_rule(&self) -> Result<NinjaRule, String> {
         Ok(
             if self.0.rule.starts_with("c_LINKER") || self.0.rule.starts_with("cpp_LINKER") {
-                let (_, link_flags) = self.get_link_flags();
-                if link_flags.contains(&String::from("-fPIC")) {
-                    NinjaRule::SharedLibrary
-                } else {
-                    NinjaRule::Binary
+                match self.0.variables.get(TARGET_TYPE).map(String::as_str) {
+                    Some("shared library" | "shared module") => NinjaRule::SharedLibrary,
+                    Some("executable") => NinjaRule::Binary,
+                    _ if self.is_shared_library() => NinjaRule::SharedLibrary,
+                    _ => NinjaRule::Binary,
                 }
+            } else if let Some(rust_crate) = self.get_rust_crate()? {
+                NinjaRule::Rust(rust_crate.crate_type)
             } else if self.0.rule == "STATIC_LINKER" {
                 NinjaRule::StaticLibrary
             } else if self.0.rule == "CUSTOM_COMMAND" || self.0.rule == "CUSTOM_COMMAND_DEP" {
@@ -80,6 +188,12 @@ impl NinjaTarget for MesonNinjaTarget {
         };
         common::get_libs_shared(args)
     }
+    fn get_libs_ordered(&self) -> Vec<(PathBuf, common::LibraryKind)> {
+        let Some(args) = self.0.variables.get("LINK_ARGS") else {
+            return Vec::new();
+        };
+        common::get_link_libraries(args)
+    }
     fn get_link_flags(&self) -> (Option<PathBuf>, Vec<String>) {
         let Some(args) = self.0.variables.get("LINK_ARGS") else {
             return (None, Vec::new());
@@ -129,4 +243,28 @@ impl NinjaTarget for MesonNinjaTarget {
             .collect::<Vec<&str>>();
         common::get_cflags(&cflags.join(" "))
     }
+    fn get_rust_crate(&self) -> Result<Option<RustCrate>, String> {
+        if !self.0.rule.starts_with("rust_COMPILER") {
+            return Ok(None);
+        }
+        let Some(args) = self.0.variables.get("ARGS") else {
+            return error!("No ARGS in: {self:#?}");
+        };
+        Ok(Some(common::get_rust_crate(args)?))
+    }
+    // 'both_libraries' build a shared and a static library from the same objects.
+    fn get_variants(&self) -> Vec<PathBuf> {
+        match self.0.variables.get(TARGET_VARIANTS) {
+            Some(variants) => variants.split("\n").map(PathBuf::from).collect(),
+            None => Vec::new(),
+        }
+    }
+    fn get_language(&self) -> Option<SourceLanguage> {
+        // Preprocessed assembly sources are built by the C compiler rule.
+        if self.0.rule.starts_with("nasm_COMPILER") || self.0.rule.starts_with("asm_COMPILER") {
+            Some(SourceLanguage::Asm)
+        } else {
+            common::get_language(&self.0.inputs)
+        }
+    }
 }
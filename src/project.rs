@@ -3,6 +3,7 @@
 
 use std::collections::HashMap;
 
+use crate::compile_commands::*;
 use crate::context::*;
 use crate::ninja_parser::*;
 use crate::ninja_target::common::*;
@@ -166,6 +167,12 @@ pub trait Project {
     fn map_tool_module(&self, _tool_module: &Path) -> Option<PathBuf> {
         None
     }
+    // TOOLCHAIN FUNCTIONS
+    // Toolchains other than the default one of the generator, building for the host unless they
+    // build for Android ('clang_x64' vs 'android_clang_arm').
+    fn is_host_toolchain(&self, toolchain: &str) -> bool {
+        !toolchain.contains("android")
+    }
     // FILTER FUNCTIONS
     fn filter_cflag(&self, _cflag: &str) -> bool {
         true
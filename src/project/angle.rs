@@ -76,7 +76,7 @@ impl Angle {
         ));
         SoongPackage::default().generate(
             NinjaTargetsToGenMap::from(&targets),
-            parse_build_ninja::<GnNinjaTarget>(&self.build_path)?,
+            parse_build_ninja("gn", &self.build_path, ctx)?,
             &self.src_path,
             ndk_path,
             &self.build_path,
@@ -164,9 +164,10 @@ impl Project for Angle {
 
         let default_module = SoongModule::new("cc_defaults")
             .add_prop("name", SoongProp::Str(String::from(DEFAULTS)))
+            .add_props(package.get_common_props(DEFAULTS, vec!["cflags"]))
             .add_props(package.get_props(
                 "angle_obj_libpreprocessor_a",
-                vec!["cflags", "local_include_dirs", "shared_libs", "stl", "arch"],
+                vec!["local_include_dirs", "shared_libs", "stl", "arch"],
             )?);
 
         package
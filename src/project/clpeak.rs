@@ -40,7 +40,7 @@ impl Project for Clpeak {
         )
         .generate(
             NinjaTargetsToGenMap::from(&[target_typed!("clpeak", "cc_benchmark", "clpeak")]),
-            parse_build_ninja::<CmakeNinjaTarget>(&build_path)?,
+            parse_build_ninja("cmake", &build_path, ctx)?,
             &self.src_path,
             &ndk_path,
             &build_path,
@@ -50,7 +50,7 @@ impl Project for Clspv {
         )
         .generate(
             NinjaTargetsToGenMap::from(&Dep::ClspvTargets.get_ninja_targets(projects_map)?),
-            parse_build_ninja::<CmakeNinjaTarget>(&self.build_path)?,
+            parse_build_ninja("cmake", &self.build_path, ctx)?,
             &src_path,
             &ndk_path,
             &self.build_path,
@@ -54,7 +54,7 @@ impl Project for Fwupd {
                 target!("src/fwupdmgr", "fwupdmgr"),
                 target!("src/fwupd-binder", "fwupd-binder"),
             ]),
-            parse_build_ninja::<MesonNinjaTarget>(&self.build_path)?,
+            parse_build_ninja("meson", &self.build_path, ctx)?,
             &self.src_path,
             &ndk_path,
             &self.build_path,
@@ -45,7 +45,7 @@ impl Project for LibCLC {
         )
         .generate(
             NinjaTargetsToGenMap::from(&Dep::LibclcBins.get_ninja_targets(projects_map)?),
-            parse_build_ninja::<CmakeNinjaTarget>(&build_path)?,
+            parse_build_ninja("cmake", &build_path, ctx)?,
             &self.src_path,
             Path::new("<no_sdk>"),
             &build_path,
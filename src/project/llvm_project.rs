@@ -43,7 +43,7 @@ impl Project for LlvmProject {
         )
         .generate(
             NinjaTargetsToGenMap::from(&Dep::LlvmProjectTargets.get_ninja_targets(projects_map)?),
-            parse_build_ninja::<CmakeNinjaTarget>(&build_path)?,
+            parse_build_ninja("cmake", &build_path, ctx)?,
             &src_path,
             &ndk_path,
             &build_path,
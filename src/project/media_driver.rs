@@ -45,7 +45,7 @@ impl Project for MediaDriver {
                 "media_driver/iHD_drv_video.so",
                 "iHD_drv_video"
             )]),
-            parse_build_ninja::<CmakeNinjaTarget>(&build_path)?,
+            parse_build_ninja("cmake", &build_path, ctx)?,
             &self.src_path,
             &ndk_path,
             &build_path,
@@ -15,7 +15,7 @@ pub trait Mesa3dProject {
         build_path: &Path,
         ndk_path: &Path,
         meson_generated: &str,
-        targets_map: NinjaTargetsMap<MesonNinjaTarget>,
+        targets_map: NinjaTargetsMap<Box<dyn NinjaTarget>>,
     ) -> Result<SoongPackage, String>;
     fn get_default_module(&self, package: &SoongPackage) -> Result<SoongModule, String>;
     fn get_raw_suffix(&self, common_raw_prop: &'static str) -> String;
@@ -30,7 +30,7 @@ pub trait Mesa3dProject {
     }
     fn extract_assets_to_filter(
         targets: &NinjaTargetsToGenMap,
-        targets_map: &NinjaTargetsMap<MesonNinjaTarget>,
+        targets_map: &NinjaTargetsMap<Box<dyn NinjaTarget>>,
     ) -> Result<Vec<PathBuf>, String> {
         let mut assets = Vec::new();
         targets_map.traverse_from(targets.get_targets(), false, |target| {
@@ -45,11 +45,7 @@ pub trait Mesa3dProject {
                 }) {
                     let mut outputs = target.get_outputs().clone();
                     outputs.extend(target.get_implicit_ouputs().clone());
-                    assets.extend(
-                        outputs
-                            .into_iter()
-                            .map(|output| strip_prefix(output, "n2s")),
-                    );
+                    assets.extend(outputs);
                 }
             }
             Ok(true)
@@ -102,7 +98,7 @@ where
             self,
         )?;
 
-        let targets = parse_build_ninja::<MesonNinjaTarget>(&build_path)?;
+        let targets = parse_build_ninja("meson", &build_path, ctx)?;
         const MESON_GENERATED: &str = "meson_generated";
         let mut package = self.create_package(
             ctx,
@@ -52,7 +52,7 @@ impl mesa3d_desktop::Mesa3dProject for Mesa3DDesktopIntel {
         build_path: &Path,
         ndk_path: &Path,
         meson_generated: &str,
-        targets_map: NinjaTargetsMap<MesonNinjaTarget>,
+        targets_map: NinjaTargetsMap<Box<dyn NinjaTarget>>,
     ) -> Result<SoongPackage, String> {
         self.src_path = PathBuf::from(src_path);
 
@@ -32,7 +32,7 @@ impl mesa3d_desktop::Mesa3dProject for Mesa3DDesktopPanVK {
         build_path: &Path,
         ndk_path: &Path,
         meson_generated: &str,
-        targets_map: NinjaTargetsMap<MesonNinjaTarget>,
+        targets_map: NinjaTargetsMap<Box<dyn NinjaTarget>>,
     ) -> Result<SoongPackage, String> {
         self.src_path = PathBuf::from(src_path);
         let targets_to_gen = NinjaTargetsToGenMap::from(&[
@@ -109,7 +109,7 @@ impl Project for OpenclCts {
         )
         .generate(
             NinjaTargetsToGenMap::from(&targets),
-            parse_build_ninja::<CmakeNinjaTarget>(&self.build_path)?,
+            parse_build_ninja("cmake", &self.build_path, ctx)?,
             &self.src_path,
             &ndk_path,
             &self.build_path,
@@ -40,7 +40,7 @@ impl Project for OpenclIcdLoader {
         )
         .generate(
             NinjaTargetsToGenMap::from(&[target!("libOpenCL.so", "libOpenCL")]),
-            parse_build_ninja::<CmakeNinjaTarget>(&build_path)?,
+            parse_build_ninja("cmake", &build_path, ctx)?,
             &src_path,
             &ndk_path,
             &build_path,
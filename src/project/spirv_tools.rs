@@ -35,8 +35,7 @@ impl Project for SpirvTools {
             self,
         )?;
 
-        const GENERATED_TABLES_BODY: &str = "SPIRV-Tools_core_tables_body";
-        const GENERATED_TABLES_HEADER: &str = "SPIRV-Tools_core_tables_header";
+        const GENERATED_TABLES: &str = "SPIRV-Tools_core_tables";
         let mut package = SoongPackage::new(
             &[],
             "SPIRV-Tools_license",
@@ -45,9 +44,9 @@ impl Project for SpirvTools {
         )
         .generate(
             NinjaTargetsToGenMap::from(&Dep::SpirvToolsTargets.get_ninja_targets(projects_map)?)
-                .push(target!("core_tables_body.inc", GENERATED_TABLES_BODY))
-                .push(target!("core_tables_header.inc", GENERATED_TABLES_HEADER)),
-            parse_build_ninja::<CmakeNinjaTarget>(&self.build_path)?,
+                .push(target!("core_tables_body.inc", GENERATED_TABLES))
+                .push(target!("core_tables_header.inc", GENERATED_TABLES)),
+            parse_build_ninja("cmake", &self.build_path, ctx)?,
             &src_path,
             &ndk_path,
             &self.build_path,
@@ -72,16 +71,10 @@ impl Project for SpirvTools {
 cc_library_headers {{
     name: "SPIRV-Tools-sources",
     header_libs: ["{0}"],
-    generated_headers: [
-        "{GENERATED_TABLES_BODY}",
-        "{GENERATED_TABLES_HEADER}",
-    ],
+    generated_headers: ["{GENERATED_TABLES}"],
     export_include_dirs: ["."],
     export_header_lib_headers: ["{0}"],
-    export_generated_headers: [
-        "{GENERATED_TABLES_BODY}",
-        "{GENERATED_TABLES_HEADER}",
-    ],
+    export_generated_headers: ["{GENERATED_TABLES}"],
     vendor_available: true,
 }}
 "#,
@@ -10,10 +10,17 @@ pub struct UnitTest {
     ctx: Context,
 }
 
-fn generate_package<T>(targets: Vec<T>, project: &mut UnitTest) -> Result<String, String>
-where
-    T: NinjaTarget,
-{
+fn generate_package(
+    (targets, defaults): (Vec<Box<dyn NinjaTarget>>, Vec<PathBuf>),
+    project: &mut UnitTest,
+) -> Result<String, String> {
+    // Without any target in the config file, generate the Ninja default targets.
+    if project.targets_to_gen.is_empty() {
+        project.targets_to_gen = defaults
+            .iter()
+            .map(|target| target!(path_to_string(target)))
+            .collect();
+    }
     SoongPackage::new(&[], "unittest_license", &[], &[])
         .generate(
             NinjaTargetsToGenMap::from(&project.targets_to_gen),
@@ -48,18 +55,27 @@ impl Project for UnitTest {
         print_verbose!("'{}'", file_name(&test_path));
         let config = read_file(&test_path.join("config"))?;
         let mut lines = config.lines();
-        let Some(ninja_generator) = lines.nth(0) else {
+        let Some(first_line) = lines.nth(0) else {
             return error!("Could not get ninja_generator from config file");
         };
+        // The Ninja generator can be followed by the mode to use compile_commands.json with.
+        let ninja_generator = match first_line.split_once(" ") {
+            Some((ninja_generator, mode)) => {
+                self.ctx.compile_commands = Some(CompileCommandsMode::new(mode)?);
+                ninja_generator
+            }
+            None => first_line,
+        };
         self.targets_to_gen.clear();
-        while let Some(target) = lines.nth(0) {
-            self.targets_to_gen.push(target!(target));
-        }
-        match ninja_generator {
-            "cmake" => generate_package(parse_build_ninja::<CmakeNinjaTarget>(&test_path)?, self),
-            "meson" => generate_package(parse_build_ninja::<MesonNinjaTarget>(&test_path)?, self),
-            "gn" => generate_package(parse_build_ninja::<GnNinjaTarget>(&test_path)?, self),
-            _ => return error!("Unknown Ninja Generator"),
+        while let Some(line) = lines.nth(0) {
+            self.targets_to_gen.push(match line.split_once(" ") {
+                Some((target, name)) => target!(target, name),
+                None => target!(line),
+            });
         }
+        generate_package(
+            parse_build_ninja_with_defaults(ninja_generator, &test_path, ctx)?,
+            self,
+        )
     }
 }
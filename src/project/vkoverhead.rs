@@ -39,7 +39,7 @@ impl Project for Vkoverhead {
         )
         .generate(
             NinjaTargetsToGenMap::from(&[target!("vkoverhead", "vkoverhead")]),
-            parse_build_ninja::<MesonNinjaTarget>(&build_path)?,
+            parse_build_ninja("meson", &build_path, ctx)?,
             &src_path,
             &ndk_path,
             &build_path,
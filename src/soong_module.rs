@@ -36,6 +36,7 @@ pub struct SoongNamedProp {
     name: String,
     prop: SoongProp,
     wildcard_src_path: Option<PathBuf>,
+    keep_order: bool,
 }
 
 impl SoongNamedProp {
@@ -44,6 +45,7 @@ impl SoongNamedProp {
             name: String::from(name),
             prop,
             wildcard_src_path: None,
+            keep_order: false,
         }
     }
 
@@ -55,6 +57,19 @@ impl SoongNamedProp {
         Ok(())
     }
 
+    // Print the values in their order instead of sorting them, for lists where order matters.
+    pub fn enable_keep_order(&mut self) -> Result<(), String> {
+        let SoongProp::VecStr(_) = &self.prop else {
+            return error!("Could not keep the order of a non-VecStr property");
+        };
+        self.keep_order = true;
+        Ok(())
+    }
+
+    pub fn keeps_order(&self) -> bool {
+        self.keep_order
+    }
+
     pub fn get_prop(&self) -> SoongProp {
         self.prop.clone()
     }
@@ -161,8 +176,13 @@ impl SoongNamedProp {
                 if let Some(src_path) = self.wildcard_src_path {
                     vec_str = wildcardize_paths(vec_str, &src_path);
                 }
-                vec_str.sort_unstable();
-                vec_str.dedup();
+                if self.keep_order {
+                    let mut seen = std::collections::HashSet::new();
+                    vec_str.retain(|str| seen.insert(str.clone()));
+                } else {
+                    vec_str.sort_unstable();
+                    vec_str.dedup();
+                }
                 if vec_str.len() == 1 {
                     format!("[\"{0}\"]", vec_str[0])
                 } else {
@@ -259,6 +279,7 @@ impl SoongModule {
                 let updated_prop = f(prop)?;
                 let mut updated_named_prop = SoongNamedProp::new(name, updated_prop);
                 updated_named_prop.wildcard_src_path = named_prop.wildcard_src_path;
+                updated_named_prop.keep_order = named_prop.keep_order;
                 self.props.insert(index, updated_named_prop);
                 return Ok(true);
             }
@@ -3,6 +3,7 @@
 
 use std::str;
 
+use crate::compile_commands::*;
 use crate::context::*;
 use crate::ninja_target::common::*;
 use crate::ninja_target::*;
@@ -16,10 +17,126 @@ pub struct SoongModuleGeneratorInternals {
     pub libs: Vec<PathBuf>,
     pub custom_cmd_inputs: Vec<PathBuf>,
     pub tools_module: Vec<PathBuf>,
+    // 'cc_defaults' whose values are already removed from the modules using them.
+    pub filtered_defaults: Vec<String>,
     python_binaries: std::collections::HashSet<String>,
     python_libraries: std::collections::HashSet<String>,
 }
 
+// Option set by a flag, objects setting an option to different values cannot be built by the same
+// module.
+fn get_flag_option(flag: &str) -> Option<String> {
+    if let Some(define) = flag.strip_prefix("-D") {
+        let name = define.split_once("=").map_or(define, |(name, _)| name);
+        return Some(format!("-D{name}"));
+    } else if flag.starts_with("-O") {
+        return Some(String::from("-O"));
+    } else if flag.starts_with("-std=") {
+        return Some(String::from("-std="));
+    } else if let Some(warning) = flag.strip_prefix("-W") {
+        return Some(format!(
+            "-W{}",
+            warning.strip_prefix("no-").unwrap_or(warning)
+        ));
+    }
+    for prefix in ["-f", "-m"] {
+        if let Some(option) = flag.strip_prefix(prefix) {
+            let option = option.strip_prefix("no-").unwrap_or(option);
+            let name = option.split_once("=").map_or(option, |(name, _)| name);
+            return Some(format!("{prefix}{name}"));
+        }
+    }
+    None
+}
+
+// Objects of a target whose flags can be set by a single module.
+#[derive(Default)]
+struct ObjectsBucket {
+    sources: Vec<String>,
+    cflags: Vec<String>,
+    conlyflags: Option<Vec<String>>,
+    cppflags: Option<Vec<String>>,
+    asflags: Vec<String>,
+    options: std::collections::HashMap<(Option<SourceLanguage>, String), String>,
+}
+
+impl ObjectsBucket {
+    // Options only conflict with the ones of the objects of the same language, 'conlyflags' and
+    // 'cppflags' keep them apart. An option set several times by an object only takes its last
+    // value.
+    fn accept(&mut self, language: Option<SourceLanguage>, flags: &[String]) -> bool {
+        let options = flags
+            .iter()
+            .filter_map(|flag| Some(((language, get_flag_option(flag)?), flag.clone())))
+            .collect::<std::collections::HashMap<_, _>>();
+        if options
+            .iter()
+            .any(|(option, flag)| self.options.get(option).is_some_and(|value| value != flag))
+        {
+            return false;
+        }
+        self.options.extend(options);
+        true
+    }
+    fn add_flags(&mut self, language: Option<SourceLanguage>, flags: Vec<String>) {
+        match language {
+            Some(SourceLanguage::Asm) => self.asflags.extend(flags),
+            Some(SourceLanguage::C) => self.conlyflags.get_or_insert_with(Vec::new).extend(flags),
+            Some(SourceLanguage::Cpp) => self.cppflags.get_or_insert_with(Vec::new).extend(flags),
+            None => self.cflags.extend(flags),
+        }
+    }
+    // Flags of both C and C++ objects are common to every source, the others only apply to the
+    // language of the objects they come from. Flags only valid for C++ always stay in 'cppflags'.
+    fn split_language_flags(&mut self) -> (Vec<String>, Vec<String>) {
+        let (Some(conlyflags), Some(cppflags)) = (&self.conlyflags, &self.cppflags) else {
+            self.cflags
+                .extend(self.conlyflags.take().unwrap_or_default());
+            let (cppflags, cflags): (Vec<_>, Vec<_>) = self
+                .cppflags
+                .take()
+                .unwrap_or_default()
+                .into_iter()
+                .partition(|flag| is_cpp_only_flag(flag));
+            self.cflags.extend(cflags);
+            return (Vec::new(), cppflags);
+        };
+        self.cflags.extend(
+            conlyflags
+                .iter()
+                .filter(|flag| cppflags.contains(flag))
+                .cloned()
+                .collect::<Vec<_>>(),
+        );
+        let only = |flags: &Vec<String>| {
+            flags
+                .iter()
+                .filter(|flag| !self.cflags.contains(flag))
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        (only(conlyflags), only(cppflags))
+    }
+}
+
+// Flags rejected by the C compiler, or only meaningful for C++.
+const CPP_ONLY_FLAGS: &[&str] = &[
+    "-std=c++",
+    "-std=gnu++",
+    "-fvisibility-inlines-hidden",
+    "-frtti",
+    "-fno-rtti",
+];
+
+fn is_cpp_only_flag(flag: &str) -> bool {
+    CPP_ONLY_FLAGS.iter().any(|prefix| flag.starts_with(prefix))
+}
+
+// Ninja variables are already evaluated, '$' needs to be escaped again for Soong.
+fn escape_flag(flag: &str) -> String {
+    flag.replace("$", "$$")
+}
+
 pub struct SoongModuleGenerator<'a, T>
 where
     T: NinjaTarget,
@@ -31,6 +148,9 @@ where
     gen_build_prefix: Option<&'a str>,
     targets_map: &'a NinjaTargetsMap<'a, T>,
     targets_to_gen: &'a NinjaTargetsToGenMap,
+    compile_commands: Option<(CompileCommands, CompileCommandsMode)>,
+    // Module names of the object libraries to generate, indexed by their objects.
+    object_libraries: std::collections::HashMap<PathBuf, String>,
     project: &'a dyn Project,
 }
 
@@ -47,7 +167,7 @@ where
         targets_to_gen: &'a NinjaTargetsToGenMap,
         project: &'a dyn Project,
     ) -> Self {
-        Self {
+        let mut gen = Self {
             internals: SoongModuleGeneratorInternals::default(),
             src_path,
             ndk_path,
@@ -55,13 +175,74 @@ where
             gen_build_prefix,
             targets_map,
             targets_to_gen,
+            compile_commands: None,
+            object_libraries: std::collections::HashMap::new(),
             project,
+        };
+        for target_name in targets_to_gen.get_targets() {
+            let Some(target) = targets_map.get(&target_name) else {
+                continue;
+            };
+            if let Ok(NinjaRule::ObjectLibrary) = target.get_rule() {
+                let module_name = gen.get_module_name(&target_name);
+                for input in target.get_inputs() {
+                    gen.object_libraries
+                        .insert(input.clone(), module_name.clone());
+                }
+            }
         }
+        gen
+    }
+    pub fn with_compile_commands(
+        mut self,
+        compile_commands: Option<(CompileCommands, CompileCommandsMode)>,
+    ) -> Self {
+        self.compile_commands = compile_commands;
+        self
     }
     pub fn delete(self) -> SoongModuleGeneratorInternals {
         self.internals
     }
 
+    fn get_module_name(&self, target_name: &Path) -> String {
+        path_to_id(match self.targets_to_gen.get_name(target_name) {
+            Some(name) => name,
+            None => Path::new(self.project.get_name()).join(target_name),
+        })
+    }
+
+    // Targets of host toolchains are built by the host variant of their module type.
+    fn get_host_module_type(&self, target: &T, module_type: &str) -> String {
+        let is_host = target
+            .get_toolchain()
+            .is_some_and(|toolchain| self.project.is_host_toolchain(&toolchain));
+        String::from(match module_type {
+            "cc_binary" if is_host => "cc_binary_host",
+            "cc_test" if is_host => "cc_test_host",
+            "cc_library_static" if is_host => "cc_library_host_static",
+            "cc_library_shared" if is_host => "cc_library_host_shared",
+            "rust_binary" if is_host => "rust_binary_host",
+            "rust_library" if is_host => "rust_library_host",
+            "rust_ffi_static" if is_host => "rust_ffi_host_static",
+            "rust_ffi_shared" if is_host => "rust_ffi_host_shared",
+            "java_library" if is_host => "java_library_host",
+            _ => module_type,
+        })
+    }
+    fn get_module_type(&self, target: &T, module_type: &str) -> String {
+        match self.targets_to_gen.get_module_name(&target.get_name()) {
+            Some(module_type) => module_type,
+            None => self.get_host_module_type(target, module_type),
+        }
+    }
+
+    pub fn is_object_library(&self, target: &T) -> bool {
+        let module_name = self.get_module_name(&target.get_name());
+        self.object_libraries
+            .values()
+            .any(|object_library| *object_library == module_name)
+    }
+
     pub fn filter_target(&self, target: &T) -> bool {
         let target_name = target.get_name();
         debug_project!("filter_target({target_name:#?})");
@@ -95,7 +276,7 @@ where
             self.project.filter_define(&def)
         }))
         .iter()
-        .map(|def| format!("-D{def}"))
+        .map(|def| escape_flag(&format!("-D{def}")))
         .collect()
     }
     fn get_cflags(&self, cflags: Vec<String>) -> Vec<String> {
@@ -105,6 +286,7 @@ where
                 debug_project!("filter_cflags({cflag})");
                 self.project.filter_cflag(cflag)
             })
+            .map(|cflag| escape_flag(&cflag))
             .collect()
     }
     fn get_includes(&self, includes: Vec<PathBuf>) -> Vec<String> {
@@ -118,12 +300,11 @@ where
     }
     fn get_libs(
         &mut self,
-        libs: Vec<PathBuf>,
+        libs: Vec<(PathBuf, LibraryKind)>,
         module_name: &String,
-        kind: LibraryKind,
     ) -> Vec<(String, LibraryKind)> {
         libs.into_iter()
-            .filter_map(|lib| {
+            .filter_map(|(lib, kind)| {
                 debug_project!("filter_lib({lib:#?})");
                 if !self.project.filter_lib(&path_to_string(&lib)) {
                     return None;
@@ -151,6 +332,31 @@ where
             })
             .collect()
     }
+    fn get_object_flags(&self, target: &T) -> Result<CompileCommandFlags, String> {
+        let flags = CompileCommandFlags {
+            defines: target.get_defines(),
+            includes: target.get_includes(self.build_path),
+            cflags: target.get_cflags(),
+        };
+        let (Some((compile_commands, mode)), Some(source)) =
+            (&self.compile_commands, target.get_inputs().first())
+        else {
+            return Ok(flags);
+        };
+        let output = target.get_name();
+        let Some(compile_command_flags) = compile_commands.get(&output, source) else {
+            return Ok(flags);
+        };
+        match mode {
+            CompileCommandsMode::Check => {
+                if let Err(err) = compile_command_flags.check(&flags) {
+                    return error!("{output:#?} flags differ from compile_commands.json:\n{err}");
+                }
+                Ok(flags)
+            }
+            CompileCommandsMode::Override => Ok(compile_command_flags.clone()),
+        }
+    }
     fn get_link_flags(&self, link_flags: Vec<String>) -> Vec<String> {
         link_flags
             .into_iter()
@@ -158,6 +364,7 @@ where
                 debug_project!("filter_link_flag({flag})");
                 self.project.filter_link_flag(flag)
             })
+            .map(|flag| escape_flag(&flag))
             .collect()
     }
     fn get_generated_assets(
@@ -168,9 +375,6 @@ where
         let mut gen_assets = Vec::new();
         self.targets_map
             .traverse_from(target.get_outputs().clone(), !filter_header, |target| {
-                if target.get_outputs().len() > 1 {
-                    return Ok(false);
-                }
                 if let NinjaRule::CustomCommand(_) = target.get_rule()? {
                     gen_assets.extend(target.get_outputs().clone())
                 }
@@ -179,7 +383,9 @@ where
         Ok(gen_assets
             .iter()
             .filter_map(|asset| {
-                if file_ext(asset).starts_with(if filter_header { "c" } else { "h" }) {
+                // Compilable outputs are sources, every other output is a header.
+                let is_source = get_language(&[asset.clone()]).is_some();
+                if is_source == filter_header {
                     return None;
                 }
                 debug_project!(
@@ -192,14 +398,11 @@ where
                     self.internals.gen_assets.push(PathBuf::from(asset));
                     return None;
                 }
-                let Some(target) = self.targets_map.get(asset) else {
+                if self.targets_map.get(asset).is_none() {
                     self.internals.gen_assets.push(PathBuf::from(asset));
                     return None;
-                };
-                Some(match self.targets_to_gen.get_name(asset) {
-                    Some(name) => path_to_string(name),
-                    None => path_to_id(Path::new(self.project.get_name()).join(target.get_name())),
-                })
+                }
+                Some(self.get_dep_id(asset))
             })
             .collect())
     }
@@ -209,33 +412,6 @@ where
     fn get_generated_sources(&mut self, target: &T) -> Result<Vec<String>, String> {
         self.get_generated_assets(target, false)
     }
-    fn defines_conflict(
-        defines: &mut std::collections::HashMap<String, String>,
-        cflags: &Vec<String>,
-    ) -> bool {
-        let new_defines = cflags
-            .iter()
-            .filter_map(|cflag| {
-                let Some(def) = cflag.strip_prefix("-D") else {
-                    return None;
-                };
-                let Some((var, val)) = def.split_once("=") else {
-                    return None;
-                };
-                Some((String::from(var), String::from(val)))
-            })
-            .collect::<Vec<_>>();
-        if new_defines.iter().any(|(var, val)| {
-            let Some(ref_val) = defines.get(var) else {
-                return false;
-            };
-            val != ref_val
-        }) {
-            return true;
-        }
-        defines.extend(new_defines);
-        false
-    }
     pub fn generate_object(
         &mut self,
         module_type: &str,
@@ -243,82 +419,143 @@ where
         ctx: &Context,
     ) -> Result<Vec<SoongModule>, String> {
         let target_name = target.get_name();
-        let module_name = path_to_id(match self.targets_to_gen.get_name(&target_name) {
-            Some(name) => name,
-            None => Path::new(self.project.get_name()).join(&target_name),
-        });
+        let module_name = self.get_module_name(&target_name);
         let mut modules = Vec::new();
-        let mut cflags = Vec::new();
+        let mut buckets = vec![ObjectsBucket::default()];
         let mut includes = Vec::new();
-        let mut sources = Vec::new();
         let mut libs = Vec::new();
         let mut whole_static_libs = Vec::new();
-        let mut defines = std::collections::HashMap::new();
+        let mut objs = Vec::new();
         for input in target.get_inputs() {
+            if let Some(object_library) = self.object_libraries.get(input) {
+                if *object_library != module_name {
+                    objs.push(object_library.clone());
+                    continue;
+                }
+            }
             let Some(input_target) = self.targets_map.get(input) else {
-                sources.push(path_to_string(strip_prefix(
+                buckets[0].sources.push(path_to_string(strip_prefix(
                     canonicalize_path(input, self.build_path),
                     self.src_path,
                 )));
                 continue;
             };
 
-            let mut input_cflags = self.get_defines(input_target.get_defines());
-            input_cflags.extend(self.get_cflags(input_target.get_cflags()));
-            if !Self::defines_conflict(&mut defines, &input_cflags) {
-                libs.extend(self.get_libs(
-                    input_target.get_libs_static_whole(),
-                    &module_name,
-                    LibraryKind::StaticWhole,
-                ));
-                libs.extend(self.get_libs(
-                    input_target.get_libs_static(),
-                    &module_name,
-                    LibraryKind::Static,
-                ));
-                libs.extend(self.get_libs(
-                    input_target.get_libs_shared(),
-                    &module_name,
-                    LibraryKind::Shared,
-                ));
-                sources.extend(self.get_sources(input_target.get_sources(self.build_path)?));
-                includes.extend(self.get_includes(input_target.get_includes(self.build_path)));
-                cflags.extend(input_cflags);
-            } else {
-                modules.extend(self.generate_object("cc_library_static", input_target, ctx)?);
-                whole_static_libs.push(path_to_id(Path::new(self.project.get_name()).join(input)));
-                continue;
-            }
+            let object_flags = self.get_object_flags(input_target)?;
+            let mut input_cflags = self.get_defines(object_flags.defines);
+            input_cflags.extend(self.get_cflags(object_flags.cflags));
+            let language = input_target.get_language();
+            let index = match buckets
+                .iter_mut()
+                .position(|bucket| bucket.accept(language, &input_cflags))
+            {
+                Some(index) => index,
+                None => {
+                    let mut bucket = ObjectsBucket::default();
+                    bucket.accept(language, &input_cflags);
+                    buckets.push(bucket);
+                    buckets.len() - 1
+                }
+            };
+            libs.extend(self.get_libs(input_target.get_libs_ordered(), &module_name));
+            let sources = self.get_sources(input_target.get_sources(self.build_path)?);
+            includes.extend(self.get_includes(object_flags.includes));
+            buckets[index].sources.extend(sources);
+            buckets[index].add_flags(language, input_cflags);
         }
         includes.extend(self.get_includes(target.get_includes(self.build_path)));
-        cflags.extend(self.get_defines(target.get_defines()));
-        cflags.extend(self.get_cflags(target.get_cflags()));
+        let mut target_cflags = self.get_defines(target.get_defines());
+        target_cflags.extend(self.get_cflags(target.get_cflags()));
+        let mut buckets = buckets
+            .into_iter()
+            .map(|mut bucket| {
+                bucket.cflags.extend(target_cflags.clone());
+                let (conlyflags, cppflags) = bucket.split_language_flags();
+                (bucket, conlyflags, cppflags)
+            })
+            .collect::<Vec<_>>();
+        let mut generated_headers = self.get_generated_headers(target)?;
+        // Objects built with conflicting options are split into static libraries sharing a
+        // 'cc_defaults' module with their common flags.
+        let mut defaults = Vec::new();
+        if buckets.len() > 1 {
+            let defaults_name = format!("{module_name}_defaults");
+            let common_cflags = buckets[0]
+                .0
+                .cflags
+                .iter()
+                .filter(|flag| {
+                    buckets[1..]
+                        .iter()
+                        .all(|(bucket, _, _)| bucket.cflags.contains(flag))
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            // Values of the defaults are not repeated by the modules using them.
+            for (bucket, _, _) in &mut buckets {
+                bucket.cflags.retain(|flag| !common_cflags.contains(flag));
+            }
+            modules.push(
+                SoongModule::new("cc_defaults")
+                    .add_prop("name", SoongProp::Str(defaults_name.clone()))
+                    .add_prop("cflags", SoongProp::VecStr(common_cflags))
+                    .add_prop("local_include_dirs", SoongProp::VecStr(includes.clone()))
+                    .add_prop(
+                        "generated_headers",
+                        SoongProp::VecStr(generated_headers.clone()),
+                    ),
+            );
+            self.internals.filtered_defaults.push(defaults_name.clone());
+            defaults.push(defaults_name);
+            for (index, (bucket, conlyflags, cppflags)) in buckets.drain(1..).enumerate() {
+                let bucket_name = format!("{module_name}_flags{0}", index + 1);
+                let mut srcs_prop = SoongNamedProp::new("srcs", SoongProp::VecStr(bucket.sources));
+                if ctx.wildcardize_paths {
+                    srcs_prop.enable_wildcard(&self.src_path)?;
+                }
+                let bucket_module =
+                    SoongModule::new(&self.get_host_module_type(target, "cc_library_static"))
+                        .add_prop("name", SoongProp::Str(bucket_name.clone()))
+                        .add_prop("defaults", SoongProp::VecStr(defaults.clone()))
+                        .add_named_prop(srcs_prop)
+                        .add_prop("cflags", SoongProp::VecStr(bucket.cflags))
+                        .add_prop("conlyflags", SoongProp::VecStr(conlyflags))
+                        .add_prop("cppflags", SoongProp::VecStr(cppflags))
+                        .add_prop("asflags", SoongProp::VecStr(bucket.asflags));
+                modules.push(self.project.extend_module(&target_name, bucket_module)?);
+                whole_static_libs.push(bucket_name);
+            }
+            includes.clear();
+            generated_headers.clear();
+        }
+        let (bucket, conlyflags, cppflags) = buckets.remove(0);
 
-        let generated_headers = self.get_generated_headers(target)?;
         let generated_sources = self.get_generated_sources(target)?;
         let (version_script, link_flags) = target.get_link_flags();
         let link_flags = self.get_link_flags(link_flags);
-        libs.extend(self.get_libs(
-            target.get_libs_static_whole(),
-            &module_name,
-            LibraryKind::StaticWhole,
-        ));
-        libs.extend(self.get_libs(target.get_libs_static(), &module_name, LibraryKind::Static));
-        libs.extend(self.get_libs(target.get_libs_shared(), &module_name, LibraryKind::Shared));
+        libs.extend(self.get_libs(target.get_libs_ordered(), &module_name));
         whole_static_libs.extend(libs.iter().filter_map(|(lib, kind)| {
             if *kind != LibraryKind::StaticWhole {
                 return None;
             }
             Some(lib.clone())
         }));
+        // Libraries linked as a whole are not linked again.
         let static_libs = libs
             .iter()
             .filter_map(|(lib, kind)| {
-                if *kind != LibraryKind::Static {
+                if *kind != LibraryKind::Static || whole_static_libs.contains(lib) {
                     return None;
                 }
                 Some(lib.clone())
             })
+            .collect::<Vec<_>>();
+        // A static library repeated in the link order is linked after every library needing it.
+        let static_libs = static_libs
+            .iter()
+            .enumerate()
+            .filter(|(index, lib)| !static_libs[index + 1..].contains(lib))
+            .map(|(_, lib)| lib.clone())
             .collect();
         let shared_libs = libs
             .iter()
@@ -330,12 +567,20 @@ where
             })
             .collect();
 
-        let module_type = match self.targets_to_gen.get_module_name(&target_name) {
-            Some(module_type) => module_type,
-            None => String::from(module_type),
-        };
+        // Static libraries are resolved in their link order.
+        let mut static_libs_prop =
+            SoongNamedProp::new("static_libs", SoongProp::VecStr(static_libs));
+        static_libs_prop.enable_keep_order()?;
+        let mut whole_static_libs_prop =
+            SoongNamedProp::new("whole_static_libs", SoongProp::VecStr(whole_static_libs));
+        whole_static_libs_prop.enable_keep_order()?;
+
+        let module_type = self.get_module_type(target, module_type);
         let mut module =
             SoongModule::new(&module_type).add_prop("name", SoongProp::Str(module_name));
+        if !defaults.is_empty() {
+            module = module.add_prop("defaults", SoongProp::VecStr(defaults));
+        }
         if let Some(stem) = self.targets_to_gen.get_stem(&target_name) {
             module = module.add_prop("stem", SoongProp::Str(stem));
         }
@@ -345,29 +590,189 @@ where
                 SoongProp::Str(path_to_string(strip_prefix(vs, &self.src_path))),
             );
         }
-        let mut srcs_prop = SoongNamedProp::new("srcs", SoongProp::VecStr(sources));
+        let mut srcs_prop = SoongNamedProp::new("srcs", SoongProp::VecStr(bucket.sources));
         if ctx.wildcardize_paths {
             srcs_prop.enable_wildcard(&self.src_path)?;
         }
         module = module
             .add_named_prop(srcs_prop)
-            .add_prop("cflags", SoongProp::VecStr(cflags))
+            .add_prop("cflags", SoongProp::VecStr(bucket.cflags))
+            .add_prop("conlyflags", SoongProp::VecStr(conlyflags))
+            .add_prop("cppflags", SoongProp::VecStr(cppflags))
+            .add_prop("asflags", SoongProp::VecStr(bucket.asflags))
             .add_prop("ldflags", SoongProp::VecStr(link_flags))
             .add_prop("shared_libs", SoongProp::VecStr(shared_libs))
-            .add_prop("static_libs", SoongProp::VecStr(static_libs))
-            .add_prop("whole_static_libs", SoongProp::VecStr(whole_static_libs))
+            .add_named_prop(static_libs_prop)
+            .add_named_prop(whole_static_libs_prop)
+            .add_prop("objs", SoongProp::VecStr(objs))
             .add_prop("local_include_dirs", SoongProp::VecStr(includes))
             .add_prop("generated_sources", SoongProp::VecStr(generated_sources))
             .add_prop("generated_headers", SoongProp::VecStr(generated_headers));
+        // Circular dependencies between static libraries are resolved by linking them as a group.
+        if target.has_link_group() {
+            module = module.add_prop("group_static_libs", SoongProp::Bool(true));
+        }
 
         modules.push(self.project.extend_module(&target_name, module)?);
         Ok(modules)
     }
 
+    pub fn generate_rust(
+        &mut self,
+        module_type: &str,
+        target: &T,
+    ) -> Result<Vec<SoongModule>, String> {
+        let Some(rust_crate) = target.get_rust_crate()? else {
+            return error!("No crate in: {target:#?}");
+        };
+        let target_name = target.get_name();
+        let module_name = self.get_module_name(&target_name);
+        // Only the root of the crate is an input of rustc, its other sources are implicit deps.
+        let srcs = self.get_sources(
+            target
+                .get_inputs()
+                .iter()
+                .take(1)
+                .map(|input| canonicalize_path(input, self.build_path))
+                .collect(),
+        );
+        let mut rustlibs = Vec::new();
+        let mut proc_macros = Vec::new();
+        for (_, extern_path) in rust_crate.externs {
+            let is_proc_macro = self
+                .targets_map
+                .get(&extern_path)
+                .and_then(|extern_target| extern_target.get_rust_crate().ok().flatten())
+                .is_some_and(|extern_crate| extern_crate.crate_type == RustCrateType::ProcMacro);
+            let libs = self.get_libs(vec![(extern_path, LibraryKind::Unspecified)], &module_name);
+            if is_proc_macro {
+                proc_macros.extend(libs.into_iter().map(|(lib, _)| lib));
+            } else {
+                rustlibs.extend(libs.into_iter().map(|(lib, _)| lib));
+            }
+        }
+
+        let module_type = self.get_module_type(target, module_type);
+        let mut module =
+            SoongModule::new(&module_type).add_prop("name", SoongProp::Str(module_name));
+        if let Some(stem) = self.targets_to_gen.get_stem(&target_name) {
+            module = module.add_prop("stem", SoongProp::Str(stem));
+        }
+        module = module
+            .add_prop("crate_name", SoongProp::Str(rust_crate.crate_name))
+            .add_prop("srcs", SoongProp::VecStr(srcs));
+        // Crates built without '--edition' keep the default edition of Soong.
+        if let Some(edition) = rust_crate.edition {
+            module = module.add_prop("edition", SoongProp::Str(edition));
+        }
+        module = module
+            .add_prop("cfgs", SoongProp::VecStr(rust_crate.cfgs))
+            .add_prop("features", SoongProp::VecStr(rust_crate.features))
+            .add_prop("rustlibs", SoongProp::VecStr(rustlibs))
+            .add_prop("proc_macros", SoongProp::VecStr(proc_macros));
+
+        Ok(vec![self.project.extend_module(&target_name, module)?])
+    }
+
+    fn get_inputs_with_ext(&self, target: &T, exts: &[&str]) -> Vec<String> {
+        self.get_sources(
+            target
+                .get_inputs()
+                .iter()
+                .filter(|input| exts.contains(&file_ext(input).as_str()))
+                .map(|input| canonicalize_path(input, self.build_path))
+                .collect(),
+        )
+    }
+    // Java library providing the classes of a jar, or of the sources generated by an AIDL
+    // interface.
+    fn get_java_lib(&self, input: &Path) -> Option<String> {
+        let target = self.targets_map.get(input)?;
+        match target.get_rule().ok()? {
+            NinjaRule::JavaLibrary => Some(self.get_module_name(&target.get_name())),
+            NinjaRule::AidlInterface => Some(format!(
+                "{0}-java",
+                self.get_module_name(&target.get_name())
+            )),
+            // Dex files come from the jar of a library.
+            NinjaRule::None => self.get_java_lib(
+                target
+                    .get_inputs()
+                    .iter()
+                    .find(|input| file_ext(input) == "jar")?,
+            ),
+            _ => None,
+        }
+    }
+    pub fn generate_java(&mut self, target: &T) -> Result<Vec<SoongModule>, String> {
+        let target_name = target.get_name();
+        let module_name = self.get_module_name(&target_name);
+        let srcs = self.get_inputs_with_ext(target, &["java", "kt"]);
+        let manifest = self
+            .get_sources(
+                target
+                    .get_inputs()
+                    .iter()
+                    .filter(|input| file_name(input) == "AndroidManifest.xml")
+                    .map(|input| canonicalize_path(input, self.build_path))
+                    .collect(),
+            )
+            .pop();
+        let static_libs = target
+            .get_inputs()
+            .iter()
+            .chain(target.get_implicit_deps())
+            .filter_map(|input| self.get_java_lib(input))
+            .filter(|lib| *lib != module_name)
+            .collect();
+
+        let module_type = self.get_module_type(
+            target,
+            if manifest.is_some() {
+                "android_library"
+            } else {
+                "java_library"
+            },
+        );
+        let mut module =
+            SoongModule::new(&module_type).add_prop("name", SoongProp::Str(module_name));
+        if let Some(stem) = self.targets_to_gen.get_stem(&target_name) {
+            module = module.add_prop("stem", SoongProp::Str(stem));
+        }
+        if let Some(manifest) = manifest {
+            module = module.add_prop("manifest", SoongProp::Str(manifest));
+        }
+        module = module
+            .add_prop("srcs", SoongProp::VecStr(srcs))
+            .add_prop("static_libs", SoongProp::VecStr(static_libs));
+
+        Ok(vec![self.project.extend_module(&target_name, module)?])
+    }
+    pub fn generate_aidl(&mut self, target: &T) -> Result<Vec<SoongModule>, String> {
+        let target_name = target.get_name();
+        let module_name = self.get_module_name(&target_name);
+        let srcs = self.get_inputs_with_ext(target, &["aidl"]);
+        let includes = self.get_includes(target.get_includes(self.build_path));
+
+        let mut module =
+            SoongModule::new("aidl_interface").add_prop("name", SoongProp::Str(module_name));
+        // Soong takes a single import root, the one of the interfaces of the module.
+        if let Some(include) = includes
+            .into_iter()
+            .find(|include| srcs.iter().all(|src| Path::new(src).starts_with(include)))
+        {
+            module = module.add_prop("local_include_dir", SoongProp::Str(include));
+        }
+        module = module
+            .add_prop("srcs", SoongProp::VecStr(srcs))
+            // GN builds do not freeze their interfaces.
+            .add_prop("unstable", SoongProp::Bool(true));
+
+        Ok(vec![self.project.extend_module(&target_name, module)?])
+    }
+
     fn map_cmd_output(&self, output: &Path) -> String {
-        if output.starts_with("n2s") {
-            path_to_string(output)
-        } else if let Some(output) = self.project.map_cmd_output(output) {
+        if let Some(output) = self.project.map_cmd_output(output) {
             output
         } else {
             path_to_string(output)
@@ -441,11 +846,7 @@ where
         for output in outputs {
             let output_string = path_to_string(output.parent().unwrap());
             let canonicalize_output = canonicalize_path(&output_string, self.build_path);
-            let stripped_output = strip_prefix(&canonicalize_output, self.build_path.join("n2s"));
-            let mut froms = vec![
-                path_to_string(&canonicalize_output),
-                path_to_string(canonicalize_path(&stripped_output, self.build_path)),
-            ];
+            let mut froms = vec![path_to_string(&canonicalize_output)];
             if !output_string.is_empty() {
                 froms.push(output_string);
             }
@@ -513,23 +914,43 @@ where
                     .join(" ")
             };
             let rsp = format!("$(genDir)/{rsp_file}");
-            cmd = format!("echo \\\"{rsp_inputs_string}\\\" > {rsp} && {cmd}")
-                .replace("${rspfile}", &rsp);
+            cmd = format!(
+                "echo \\\"{rsp_inputs_string}\\\" > {rsp} && {0}",
+                cmd.replace(&rsp_file, &rsp)
+            );
         }
         cmd
     }
+    // Genrules with several outputs are named after their smallest output without its extension,
+    // not to depend on the order of the outputs.
+    fn get_custom_command_name(&self, target: &T) -> String {
+        let target_name = target.get_name();
+        if let Some(name) = self.targets_to_gen.get_name(&target_name) {
+            return path_to_string(name);
+        }
+        let outputs = target.get_outputs();
+        let name = if outputs.len() > 1 {
+            outputs
+                .iter()
+                .map(|output| output.with_extension(""))
+                .min()
+                .unwrap()
+        } else {
+            target_name
+        };
+        path_to_id(Path::new(self.project.get_name()).join(name))
+    }
     fn get_dep_id(&self, input: &Path) -> String {
         let Some(target) = self.targets_map.get(&input) else {
             return path_to_id(Path::new(self.project.get_name()).join(&input));
         };
-        return if target.get_outputs().len() > 1 {
-            path_to_id(Path::new(self.project.get_name()).join(target.get_name()))
-                + "{"
-                + &path_to_string(&input)
-                + "}"
+        let module_name = self.get_custom_command_name(target);
+        // Outputs of a multi-output genrule are selected with the tag of their 'out' entry.
+        if target.get_outputs().len() > 1 {
+            module_name + "{" + &self.map_cmd_output(input) + "}"
         } else {
-            path_to_id(Path::new(self.project.get_name()).join(target.get_name()))
-        };
+            module_name
+        }
     }
     fn get_cmd_inputs(
         &self,
@@ -634,6 +1055,8 @@ where
         if cmd.starts_with("cp") {
             return Ok((Vec::new(), Vec::new(), Vec::new(), cmd));
         }
+        // Ninja variables are already evaluated, '$' needs to be escaped again for Soong.
+        cmd = cmd.replace("$", "$$");
         while let Some(index) = cmd.find("python") {
             let begin = str::from_utf8(&cmd.as_bytes()[0..index])
                 .unwrap()
@@ -769,11 +1192,7 @@ where
             .iter()
             .map(|output| path_to_string(self.map_cmd_output(output)))
             .collect();
-        let target_name = target.get_name();
-        let module_name = match self.targets_to_gen.get_name(&target_name) {
-            Some(name) => path_to_string(name),
-            None => path_to_id(Path::new(self.project.get_name()).join(target_name)),
-        };
+        let module_name = self.get_custom_command_name(target);
 
         modules.push(
             self.project.extend_custom_command(
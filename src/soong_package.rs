@@ -1,6 +1,7 @@
 // Copyright 2024 ninja-to-soong authors
 // SPDX-License-Identifier: Apache-2.0;
 
+use crate::compile_commands::*;
 use crate::context::*;
 use crate::ninja_target::*;
 use crate::project::*;
@@ -122,6 +123,9 @@ impl SoongPackage {
             return error!("Unexpected property");
         };
         for default_name in default_names {
+            if self.internals.filtered_defaults.contains(&default_name) {
+                continue;
+            }
             let Some(default_module) = self.get_module(&default_name) else {
                 return Ok(module);
             };
@@ -144,6 +148,45 @@ impl SoongPackage {
             .collect())
     }
 
+    // Flags are set per object, the defaults of several modules can only hold the values of their
+    // properties common to every module using them.
+    pub fn get_common_props(&self, defaults_name: &str, props: Vec<&str>) -> Vec<SoongNamedProp> {
+        let modules = self
+            .modules
+            .iter()
+            .filter(|module| match module.get_prop("defaults") {
+                Some(defaults) => match defaults.get_prop() {
+                    SoongProp::VecStr(defaults) => {
+                        defaults.iter().any(|name| name == defaults_name)
+                    }
+                    _ => false,
+                },
+                None => false,
+            })
+            .collect::<Vec<_>>();
+        let mut common_props = Vec::new();
+        for prop in props {
+            let mut common_values: Option<Vec<String>> = None;
+            for module in &modules {
+                let values = match module.get_prop(prop).map(|prop| prop.get_prop()) {
+                    Some(SoongProp::VecStr(values)) => values,
+                    _ => Vec::new(),
+                };
+                common_values = Some(match common_values {
+                    Some(common_values) => common_values
+                        .into_iter()
+                        .filter(|value| values.contains(value))
+                        .collect(),
+                    None => values,
+                });
+            }
+            if let Some(common_values) = common_values.filter(|values| !values.is_empty()) {
+                common_props.push(SoongNamedProp::new(prop, SoongProp::VecStr(common_values)));
+            }
+        }
+        common_props
+    }
+
     fn get_module(&self, name: &str) -> Option<&SoongModule> {
         for module in &self.modules {
             let Some(module_name_prop) = module.get_prop("name") else {
@@ -270,6 +313,13 @@ impl SoongPackage {
     where
         T: NinjaTarget,
     {
+        let targets_to_gen = targets_to_gen
+            .resolve_phony(&targets_map)?
+            .add_variants(&targets_map);
+        let compile_commands = match ctx.compile_commands {
+            Some(mode) => Some((CompileCommands::new(build_path)?, mode)),
+            None => None,
+        };
         let mut gen = SoongModuleGenerator::new(
             src_path,
             ndk_path,
@@ -278,23 +328,41 @@ impl SoongPackage {
             &targets_map,
             &targets_to_gen,
             project,
-        );
+        )
+        .with_compile_commands(compile_commands);
         targets_map.traverse_from(targets_to_gen.get_targets(), false, |target| {
             if !gen.filter_target(target) {
                 return Ok(false);
             }
             self.modules.extend(match target.get_rule()? {
                 NinjaRule::Binary => gen.generate_object("cc_binary", target, ctx)?,
+                NinjaRule::Test => gen.generate_object("cc_test", target, ctx)?,
                 NinjaRule::SharedLibrary => {
                     gen.generate_object("cc_library_shared", target, ctx)?
                 }
                 NinjaRule::StaticLibrary => {
                     gen.generate_object("cc_library_static", target, ctx)?
                 }
+                NinjaRule::ObjectLibrary if gen.is_object_library(target) => {
+                    gen.generate_object("cc_object", target, ctx)?
+                }
+                NinjaRule::Rust(crate_type) => gen.generate_rust(
+                    match crate_type {
+                        RustCrateType::Binary => "rust_binary",
+                        RustCrateType::Library => "rust_library",
+                        RustCrateType::ProcMacro => "rust_proc_macro",
+                        RustCrateType::StaticLibrary => "rust_ffi_static",
+                        RustCrateType::SharedLibrary => "rust_ffi_shared",
+                    },
+                    target,
+                )?,
+                NinjaRule::JavaLibrary => gen.generate_java(target)?,
+                NinjaRule::AidlInterface => gen.generate_aidl(target)?,
                 NinjaRule::CustomCommand(rule_cmd) => {
                     gen.generate_custom_command(target, rule_cmd)?
                 }
-                NinjaRule::None => return Ok(true),
+                // The objects of the object libraries not to generate are built by their consumers.
+                NinjaRule::ObjectLibrary | NinjaRule::None => return Ok(true),
             });
             Ok(true)
         })?;
@@ -96,8 +96,14 @@ impl SoongPackageMerger {
                 _ => return error!("unexpected prop"),
             }
         }
-        all_strings.sort_unstable();
-        all_strings.dedup();
+        let keep_order = props.iter().any(|(_, prop)| prop.keeps_order());
+        if keep_order {
+            let mut seen = std::collections::HashSet::new();
+            all_strings.retain(|string| seen.insert(string.clone()));
+        } else {
+            all_strings.sort_unstable();
+            all_strings.dedup();
+        }
         for string in all_strings {
             if inputs.iter().all(|(_, vec)| vec.contains(&string)) {
                 outputs
@@ -115,15 +121,16 @@ impl SoongPackageMerger {
                 }
             }
         }
-        Ok(outputs
+        outputs
             .into_iter()
             .map(|(target_cpu, vec)| {
-                (
-                    String::from(target_cpu),
-                    SoongNamedProp::new(prop_name, SoongProp::VecStr(vec)),
-                )
+                let mut prop = SoongNamedProp::new(prop_name, SoongProp::VecStr(vec));
+                if keep_order {
+                    prop.enable_keep_order()?;
+                }
+                Ok((String::from(target_cpu), prop))
             })
-            .collect())
+            .collect()
     }
 
     fn merge_props(
@@ -38,7 +38,7 @@ impl Project for ExternalProject {
         )
         .generate(
             NinjaTargetsToGenMap::from(&[target!("external_project_library")]),
-            parse_build_ninja::<CmakeNinjaTarget>(&test_path)?,
+            parse_build_ninja::<CmakeNinjaTarget>(&test_path, ctx)?,
             &test_path,
             &PathBuf::from("ndk"),
             &test_path,
@@ -0,0 +1 @@
+// common_a.cc
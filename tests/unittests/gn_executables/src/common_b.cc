@@ -0,0 +1 @@
+// common_b.cc
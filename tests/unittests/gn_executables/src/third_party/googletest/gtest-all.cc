@@ -0,0 +1 @@
+// gtest-all.cc
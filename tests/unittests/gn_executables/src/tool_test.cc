@@ -0,0 +1 @@
+// tool_test.cc
@@ -0,0 +1 @@
+// bar.cc
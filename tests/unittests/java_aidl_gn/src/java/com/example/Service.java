@@ -0,0 +1 @@
+// java/com/example/Service.java
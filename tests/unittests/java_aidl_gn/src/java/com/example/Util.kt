@@ -0,0 +1 @@
+// java/com/example/Util.kt
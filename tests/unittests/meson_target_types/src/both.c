@@ -0,0 +1 @@
+// both.c
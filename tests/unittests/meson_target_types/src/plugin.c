@@ -0,0 +1 @@
+// plugin.c
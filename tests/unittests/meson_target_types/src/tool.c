@@ -0,0 +1 @@
+// tool.c
@@ -0,0 +1 @@
+// derive/lib.rs
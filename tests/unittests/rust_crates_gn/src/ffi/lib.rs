@@ -0,0 +1 @@
+// ffi/lib.rs
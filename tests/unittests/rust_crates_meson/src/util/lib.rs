@@ -0,0 +1 @@
+// util/lib.rs